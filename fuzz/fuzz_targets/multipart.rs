@@ -0,0 +1,21 @@
+#![no_main]
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+use nebula_fuzz::{form_size, BOUNDARY};
+
+fuzz_target!(|data: &[u8]| {
+    let filter = nebula_form::form_filter();
+    let result = warp::test::request()
+        .method("POST")
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(data)
+        .filter(&filter);
+
+    // Malformed bodies may be rejected, but must never panic
+    if let Ok(form) = block_on(result) {
+        assert!(form_size(&form) <= data.len());
+    }
+});
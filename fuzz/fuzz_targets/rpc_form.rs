@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use nebula_form::Form;
+use nebula_fuzz::form_size;
+use nebula_rpc::{rpc, FromRPC, IntoRPC};
+use prost::Message;
+
+fuzz_target!(|data: &[u8]| {
+    let rpc_form = match rpc::Form::decode(data) {
+        Ok(rpc_form) => rpc_form,
+        Err(_) => return,
+    };
+
+    if let Ok(form) = Form::from_rpc(rpc_form) {
+        assert!(form_size(&form) <= data.len());
+
+        // Part header values are bytes on both sides, so any form that was
+        // accepted can be encoded again
+        let encoded = form.clone().into_rpc().unwrap();
+        assert_eq!(Form::from_rpc(encoded).unwrap(), form);
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use nebula_form::Form;
+use nebula_fuzz::form_size;
+
+fuzz_target!(|data: &[u8]| {
+    let body = match std::str::from_utf8(data) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+
+    if let Ok(form) = Form::from_url_encoded(body) {
+        // Invalid percent-encoded bytes become U+FFFD, which is no longer
+        // than the three bytes it replaces
+        assert!(form_size(&form) <= data.len());
+
+        let encoded = form.to_url_encoded().expect("parsed forms only hold text");
+        assert_eq!(Form::from_url_encoded(&encoded).unwrap(), form);
+    }
+});
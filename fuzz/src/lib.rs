@@ -0,0 +1,49 @@
+use nebula_form::{Field, Form};
+use std::collections::HashSet;
+
+/// The boundary used by the multipart target and its seed corpus.
+pub const BOUNDARY: &str = "nebulafuzzboundary";
+
+/// The content type the multipart parser gives nested parts that have none.
+const DEFAULT_CONTENT_TYPE: &str = "text/plain";
+
+/// Returns the name a file expanded from a nested `multipart/mixed` part was
+/// given, `name[0]`, `name[1]`, and so on, without its index.
+fn nested_name(name: &str) -> Option<&str> {
+    let (base, idx) = name.strip_suffix(']')?.rsplit_once('[')?;
+    if idx.is_empty() || !idx.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(base)
+}
+
+/// Returns the number of bytes held by a parsed form that were taken from
+/// its input: every name, text value, filename, content type, and file
+/// content.
+///
+/// Parsing never adds data of its own beyond what is left out here: the index
+/// added to the names of files expanded from a nested `multipart/mixed` part,
+/// which all share their parent's name, and the default content type of
+/// nested parts. So a form larger than the input it was parsed from means
+/// something was duplicated or allocated without bound.
+pub fn form_size(form: &Form) -> usize {
+    let mut nested = HashSet::new();
+    form.iter()
+        .map(|(name, field)| match field {
+            Field::Text(text) => name.len() + text.len(),
+            Field::File(file) => {
+                let name = match nested_name(name) {
+                    // The parent's name is only sent once
+                    Some(base) if !nested.insert(base) => 0,
+                    Some(base) => base.len(),
+                    None => name.len(),
+                };
+                let content_type = match file.content_type.as_str() {
+                    DEFAULT_CONTENT_TYPE => 0,
+                    content_type => content_type.len(),
+                };
+                name + file.filename.len() + content_type + file.bytes.len()
+            }
+        })
+        .sum()
+}
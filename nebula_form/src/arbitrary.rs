@@ -0,0 +1,167 @@
+use crate::{Field, Form, FormFile, HeaderMap};
+use http::header::{HeaderName, HeaderValue};
+use proptest::collection::{hash_map, vec};
+use proptest::prelude::*;
+
+/// Returns a strategy for field names, mixing ordinary identifiers with
+/// Unicode, whitespace, and characters that are significant in URL-encoded
+/// and multipart bodies.
+pub fn field_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z_][a-z0-9_-]{0,15}",
+        "\\PC{1,16}",
+        "[ =&%+;:'\"<>?#/\\\\\\[\\]]{1,8}",
+        "[a-z]{1,4}\\[[a-z0-9]{0,4}\\]",
+    ]
+}
+
+/// Returns a strategy for field names that `Form::to_multipart_bytes` can
+/// write without escaping and that multipart parsers read back unchanged: no
+/// quotes, backslashes, semicolons, or line breaks.
+pub fn multipart_field_name() -> impl Strategy<Value = String> {
+    field_name().prop_filter("name needs escaping in multipart", |name| {
+        !name.contains(&['"', '\\', ';', '\r', '\n'][..])
+    })
+}
+
+/// Returns a strategy for text values, including empty strings, Unicode, and
+/// line breaks.
+pub fn text() -> impl Strategy<Value = String> {
+    prop_oneof!["\\PC{0,64}", "[a-z \t\r\n]{0,32}", Just(String::new())]
+}
+
+/// Returns a strategy for content types, mostly common ones.
+pub fn content_type() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => prop::sample::select(vec![
+            "text/plain",
+            "application/octet-stream",
+            "application/pdf",
+            "image/png",
+            "image/jpeg",
+        ])
+        .prop_map(String::from),
+        1 => "[a-z]{1,8}/[a-z0-9][a-z0-9.+-]{0,15}",
+    ]
+}
+
+/// Returns a strategy for file contents: arbitrary bytes, with empty and
+/// larger files included.
+pub fn file_bytes() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        4 => vec(any::<u8>(), 0..256),
+        1 => vec(any::<u8>(), 1024..8192),
+    ]
+}
+
+/// Returns a strategy for header values with no leading or trailing
+/// whitespace, including bytes that are not ASCII, e.g. Latin-1 or UTF-8.
+fn header_value() -> impl Strategy<Value = Vec<u8>> {
+    let edge = || prop_oneof![0x21u8..=0x7e, 0x80u8..=0xff];
+    let middle = vec(prop_oneof![0x20u8..=0x7e, 0x80u8..=0xff], 0..14);
+    prop_oneof![
+        edge().prop_map(|byte| vec![byte]),
+        (edge(), middle, edge()).prop_map(|(first, mut value, last)| {
+            value.insert(0, first);
+            value.push(last);
+            value
+        }),
+    ]
+}
+
+/// Returns a strategy for extra part headers: a few `x-` headers, possibly
+/// repeated, with values that have no leading or trailing whitespace.
+pub fn part_headers() -> impl Strategy<Value = HeaderMap> {
+    vec(("x-[a-z]{1,8}", header_value()), 0..3).prop_map(|pairs| {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_bytes(&value).unwrap(),
+            );
+        }
+        headers
+    })
+}
+
+fn form_file_with(filename: impl Strategy<Value = String>) -> impl Strategy<Value = FormFile> {
+    (filename, content_type(), file_bytes(), part_headers()).prop_map(
+        |(filename, content_type, bytes, headers)| FormFile {
+            filename,
+            content_type,
+            bytes: bytes.into(),
+            headers,
+        },
+    )
+}
+
+/// Returns a strategy for files whose name and content type can be written
+/// to a multipart body without escaping.
+pub fn multipart_form_file() -> impl Strategy<Value = FormFile> {
+    form_file_with(multipart_field_name())
+}
+
+fn form_from(fields: impl Strategy<Value = Vec<(String, Field)>>) -> impl Strategy<Value = Form> {
+    fields.prop_map(|fields| {
+        let mut form = Form::new();
+        form.extend(fields.into_iter());
+        form
+    })
+}
+
+/// Returns a strategy for forms containing only text fields, which is all a
+/// URL-encoded body can hold.
+pub fn text_form() -> impl Strategy<Value = Form> {
+    form_from(
+        hash_map(field_name(), text().prop_map(Field::Text), 0..8)
+            .prop_map(|fields| fields.into_iter().collect()),
+    )
+}
+
+/// Returns a strategy for forms that survive a round trip through
+/// `Form::to_multipart_bytes` and a multipart parser.
+pub fn multipart_form() -> impl Strategy<Value = Form> {
+    let field = prop_oneof![
+        text().prop_map(Field::Text),
+        multipart_form_file().prop_map(Field::File),
+    ];
+    form_from(
+        hash_map(multipart_field_name(), field, 0..8)
+            .prop_map(|fields| fields.into_iter().collect()),
+    )
+}
+
+impl Arbitrary for FormFile {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        form_file_with(text()).boxed()
+    }
+}
+
+impl Arbitrary for Field {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            text().prop_map(Field::Text),
+            any::<FormFile>().prop_map(Field::File),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Form {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        form_from(
+            hash_map(field_name(), any::<Field>(), 0..16)
+                .prop_map(|fields| fields.into_iter().collect()),
+        )
+        .boxed()
+    }
+}
@@ -0,0 +1,450 @@
+use crate::{FormFile, MimeAllowList};
+use flate2::read::GzDecoder;
+use std::cell::Cell;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Cursor, Read};
+use std::rc::Rc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeaderMap;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn get_zip(entries: &[(&str, &[u8])]) -> FormFile {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+
+        FormFile {
+            filename: String::from("bundle.zip"),
+            content_type: String::from("application/zip"),
+            bytes: bytes.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn get_tar_gz(entries: &[(&str, &[u8])]) -> FormFile {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Default::default()));
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        let bytes = builder.into_inner().unwrap().finish().unwrap();
+
+        FormFile {
+            filename: String::from("bundle.tar.gz"),
+            content_type: String::from("application/gzip"),
+            bytes: bytes.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn get_allow_list() -> MimeAllowList {
+        MimeAllowList::new()
+            .allow("text/*")
+            .allow("application/pdf")
+    }
+
+    #[test]
+    fn list_zip_entries() {
+        let file = get_zip(&[("notes.txt", b"hello"), ("docs/report.pdf", b"%PDF-1.4")]);
+        let entries = file.archive_entries(&ArchiveLimits::new()).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "notes.txt");
+        assert_eq!(entries[0].size, 5);
+        assert_eq!(entries[0].content_type, "text/plain");
+        assert!(entries[0].compressed_size.is_some());
+        assert_eq!(entries[1].name, "docs/report.pdf");
+        assert_eq!(entries[1].content_type, "application/pdf");
+    }
+
+    #[test]
+    fn list_tar_gz_entries() {
+        let file = get_tar_gz(&[("notes.txt", b"hello"), ("report.pdf", b"%PDF-1.4")]);
+        let entries = file.archive_entries(&ArchiveLimits::new()).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "notes.txt");
+        assert_eq!(entries[0].size, 5);
+        assert_eq!(entries[0].compressed_size, None);
+        assert_eq!(entries[1].content_type, "application/pdf");
+    }
+
+    #[test]
+    fn not_an_archive() {
+        let file = FormFile {
+            filename: String::from("notes.txt"),
+            content_type: String::from("application/zip"),
+            bytes: b"hello"[..].into(),
+            headers: HeaderMap::new(),
+        };
+        match file.archive_entries(&ArchiveLimits::new()) {
+            Err(Error::Unsupported(content_type)) => assert_eq!(content_type, "application/zip"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn zip_bomb_ratio() {
+        let zeros = vec![0u8; 1024 * 1024];
+        let file = get_zip(&[("zeros.txt", &zeros)]);
+        match file.archive_entries(&ArchiveLimits::new()) {
+            Err(Error::RatioExceeded(name)) => assert_eq!(name, "zeros.txt"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let limits = ArchiveLimits::new().max_ratio(10_000);
+        assert_eq!(file.archive_entries(&limits).unwrap()[0].size, 1024 * 1024);
+    }
+
+    #[test]
+    fn zip_total_size() {
+        let file = get_zip(&[("a.txt", b"0123456789"), ("b.txt", b"0123456789")]);
+        match file.archive_entries(&ArchiveLimits::new().max_total_size(15)) {
+            Err(Error::TooLarge) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tar_gz_bomb() {
+        let zeros = vec![0u8; 1024 * 1024];
+        let file = get_tar_gz(&[("zeros.txt", &zeros)]);
+        match file.archive_entries(&ArchiveLimits::new()) {
+            Err(Error::RatioExceeded(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match file.archive_entries(&ArchiveLimits::new().max_total_size(1000).max_ratio(10_000)) {
+            Err(Error::TooLarge) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn too_many_entries() {
+        let file = get_tar_gz(&[("a.txt", b"a"), ("b.txt", b"b"), ("c.txt", b"c")]);
+        match file.archive_entries(&ArchiveLimits::new().max_entries(2)) {
+            Err(Error::TooManyEntries) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inspect_applies_allow_list() {
+        let file = get_zip(&[("notes.txt", b"hello"), ("setup.exe", b"MZ")]);
+        match file.inspect_archive(&ArchiveLimits::new(), &get_allow_list()) {
+            Err(Error::Disallowed { name, content_type }) => {
+                assert_eq!(name, "setup.exe");
+                assert!(!get_allow_list().allows(&content_type));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let file = get_tar_gz(&[("notes.txt", b"hello"), ("report.pdf", b"%PDF")]);
+        let entries = file
+            .inspect_archive(&ArchiveLimits::new(), &get_allow_list())
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+}
+
+/// Errors that can occur while inspecting an archive.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The file is not a zip or gzip-compressed tar archive. Contains the
+    /// file's declared content type.
+    Unsupported(String),
+    /// The archive could not be read.
+    Malformed(String),
+    /// The archive contains more entries than allowed.
+    TooManyEntries,
+    /// The uncompressed content of the archive is larger than allowed.
+    TooLarge,
+    /// An entry, or the archive as a whole, compresses better than allowed,
+    /// which indicates a zip bomb. Contains the name of the entry, or the
+    /// archive's filename.
+    RatioExceeded(String),
+    /// An entry has a content type that is not in the allow-list.
+    Disallowed { name: String, content_type: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Unsupported(content_type) => {
+                write!(f, "Unsupported archive type: {}", content_type)
+            }
+            Error::Malformed(msg) => write!(f, "Malformed archive: {}", msg),
+            Error::TooManyEntries => write!(f, "Archive contains too many entries"),
+            Error::TooLarge => write!(f, "Archive content is too large"),
+            Error::RatioExceeded(name) => write!(f, "Compression ratio of {} is too high", name),
+            Error::Disallowed { name, content_type } => write!(
+                f,
+                "Archive entry {} has a disallowed type: {}",
+                name, content_type
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Limits that protect against zip bombs and oversized archives.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    max_entries: usize,
+    max_total_size: u64,
+    max_ratio: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        ArchiveLimits {
+            max_entries: 1000,
+            max_total_size: 100 * 1024 * 1024,
+            max_ratio: 100,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    /// Creates the default limits: 1000 entries, 100 MiB of uncompressed
+    /// content, and a compression ratio of 100.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of entries, including directories.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Sets the maximum total size, in bytes, of the uncompressed content.
+    pub fn max_total_size(mut self, max: u64) -> Self {
+        self.max_total_size = max;
+        self
+    }
+
+    /// Sets the maximum ratio of uncompressed to compressed size. It is
+    /// checked for each entry of a zip archive, and for a tar.gz archive as a
+    /// whole, as its entries are not compressed individually.
+    pub fn max_ratio(mut self, max: u64) -> Self {
+        self.max_ratio = max.max(1);
+        self
+    }
+
+    /// The largest uncompressed size allowed for `compressed` bytes.
+    fn ratio_limit(&self, compressed: u64) -> u64 {
+        compressed.max(1).saturating_mul(self.max_ratio)
+    }
+}
+
+/// A single entry of an archive, as returned by `FormFile::archive_entries`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// The path of the entry within the archive.
+    pub name: String,
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+    /// The uncompressed size of the entry, as measured by decompressing it.
+    pub size: u64,
+    /// The compressed size of the entry. Only zip archives compress entries
+    /// individually, so this is `None` for tar archives.
+    pub compressed_size: Option<u64>,
+    /// The content type guessed from the entry's file extension.
+    pub content_type: String,
+}
+
+impl ArchiveEntry {
+    fn new(name: String, is_dir: bool, size: u64, compressed_size: Option<u64>) -> Self {
+        let content_type = if is_dir {
+            String::from("inode/directory")
+        } else {
+            mime_guess::from_path(&name)
+                .first_or_octet_stream()
+                .to_string()
+        };
+
+        ArchiveEntry {
+            name,
+            is_dir,
+            size,
+            compressed_size,
+            content_type,
+        }
+    }
+}
+
+/// A reader that fails once more than `limit` bytes have been read from it,
+/// keeping count in a shared counter.
+struct LimitedReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+    limit: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        if self.count.get() > self.limit {
+            return Err(io::Error::other("archive size limit exceeded"));
+        }
+        Ok(read)
+    }
+}
+
+/// Decompresses everything from `reader` without storing it, failing once
+/// more than `limit` bytes have been read. Returns the number of bytes read.
+fn measure(reader: impl Read, limit: u64) -> io::Result<Option<u64>> {
+    let mut limited = reader.take(limit.saturating_add(1));
+    let size = io::copy(&mut limited, &mut io::sink())?;
+    Ok(if size > limit { None } else { Some(size) })
+}
+
+fn malformed(err: impl Display) -> Error {
+    Error::Malformed(err.to_string())
+}
+
+fn zip_entries(file: &FormFile, limits: &ArchiveLimits) -> Result<Vec<ArchiveEntry>, Error> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(&file.bytes[..])).map_err(malformed)?;
+    if archive.len() > limits.max_entries {
+        return Err(Error::TooManyEntries);
+    }
+
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut total: u64 = 0;
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(malformed)?;
+        let name = entry.name().to_string();
+        let is_dir = entry.is_dir();
+        let compressed = entry.compressed_size();
+
+        // The sizes in the archive's headers can be forged, so the entry is
+        // decompressed to measure its real size.
+        let remaining = limits.max_total_size - total;
+        let limit = remaining.min(limits.ratio_limit(compressed));
+        let size = match measure(entry, limit).map_err(malformed)? {
+            Some(size) => size,
+            None if limit == remaining => return Err(Error::TooLarge),
+            None => return Err(Error::RatioExceeded(name)),
+        };
+
+        total += size;
+        entries.push(ArchiveEntry::new(name, is_dir, size, Some(compressed)));
+    }
+
+    Ok(entries)
+}
+
+fn tar_gz_entries(file: &FormFile, limits: &ArchiveLimits) -> Result<Vec<ArchiveEntry>, Error> {
+    // Tar adds headers and padding to the content, which are counted here
+    // too. Individual entries are not compressed, so only the ratio of the
+    // whole archive can be checked.
+    let remaining = limits.max_total_size;
+    let limit = remaining.min(limits.ratio_limit(file.bytes.len() as u64));
+    let count = Rc::new(Cell::new(0));
+    let reader = LimitedReader {
+        inner: GzDecoder::new(&file.bytes[..]),
+        count: count.clone(),
+        limit,
+    };
+    let limit_error = || {
+        if limit == remaining {
+            Error::TooLarge
+        } else {
+            Error::RatioExceeded(file.filename.clone())
+        }
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let iter = archive.entries().map_err(malformed)?;
+
+    for entry in iter {
+        let entry = entry.map_err(|e| {
+            if count.get() > limit {
+                limit_error()
+            } else {
+                malformed(e)
+            }
+        })?;
+
+        if entries.len() == limits.max_entries {
+            return Err(Error::TooManyEntries);
+        }
+
+        let name = entry
+            .path()
+            .map_err(malformed)?
+            .to_string_lossy()
+            .to_string();
+        let is_dir = entry.header().entry_type().is_dir();
+        let size = match measure(entry, limit) {
+            Ok(Some(size)) => size,
+            Ok(None) => return Err(limit_error()),
+            Err(_) if count.get() > limit => return Err(limit_error()),
+            Err(e) => return Err(malformed(e)),
+        };
+
+        entries.push(ArchiveEntry::new(name, is_dir, size, None));
+    }
+
+    Ok(entries)
+}
+
+impl FormFile {
+    /// Lists the entries of a zip or gzip-compressed tar archive without
+    /// extracting them to disk.
+    ///
+    /// The archive type is detected from the file's content. Every entry is
+    /// decompressed in memory to measure its real size, and an error is
+    /// returned as soon as any of `limits` is exceeded.
+    ///
+    /// Requires `features = "archives"`.
+    pub fn archive_entries(&self, limits: &ArchiveLimits) -> Result<Vec<ArchiveEntry>, Error> {
+        if self.bytes.starts_with(b"PK\x03\x04") || self.bytes.starts_with(b"PK\x05\x06") {
+            zip_entries(self, limits)
+        } else if self.bytes.starts_with(&[0x1f, 0x8b]) {
+            tar_gz_entries(self, limits)
+        } else {
+            Err(Error::Unsupported(self.content_type.clone()))
+        }
+    }
+
+    /// Like `archive_entries`, but also checks the guessed content type of
+    /// every entry other than directories against `allowed`.
+    ///
+    /// Requires `features = "archives"`.
+    pub fn inspect_archive(
+        &self,
+        limits: &ArchiveLimits,
+        allowed: &MimeAllowList,
+    ) -> Result<Vec<ArchiveEntry>, Error> {
+        let entries = self.archive_entries(limits)?;
+
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| !entry.is_dir && !allowed.allows(&entry.content_type))
+        {
+            return Err(Error::Disallowed {
+                name: entry.name.clone(),
+                content_type: entry.content_type.clone(),
+            });
+        }
+
+        Ok(entries)
+    }
+}
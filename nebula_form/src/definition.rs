@@ -0,0 +1,757 @@
+use crate::{Field, Form, MimeAllowList};
+use std::fmt::{self, Display, Formatter, Write};
+
+#[cfg(feature = "server-warp")]
+use nebula_status::{Status, StatusCode};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FormFile;
+    use crate::HeaderMap;
+
+    fn get_definition() -> FormDefinition {
+        FormDefinition::new()
+            .action("/contact")
+            .field(
+                FieldDefinition::new("name", FieldType::Text)
+                    .label("Your name")
+                    .required()
+                    .max_length(20),
+            )
+            .field(
+                FieldDefinition::new("email", FieldType::Email)
+                    .label("Email")
+                    .required(),
+            )
+            .field(
+                FieldDefinition::new("age", FieldType::Number)
+                    .min(18.0)
+                    .max(120.0),
+            )
+            .field(
+                FieldDefinition::new("topic", FieldType::Select)
+                    .label("Topic")
+                    .option("sales", "Sales")
+                    .option("support", "Support & help"),
+            )
+            .field(
+                FieldDefinition::new("attachment", FieldType::File)
+                    .accept("image/*")
+                    .max_size(8),
+            )
+    }
+
+    fn get_form() -> Form {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("Jane")));
+        form.insert("email", Field::Text(String::from("jane@example.com")));
+        form.insert("age", Field::Text(String::from("42")));
+        form.insert("topic", Field::Text(String::from("support")));
+        form
+    }
+
+    #[test]
+    fn valid_form_passes() {
+        assert_eq!(get_definition().validate(&get_form()), Ok(()));
+    }
+
+    #[test]
+    fn each_constraint_is_checked() {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("a name that is too long")));
+        form.insert("email", Field::Text(String::from("not an email")));
+        form.insert("age", Field::Text(String::from("12")));
+        form.insert("topic", Field::Text(String::from("spam")));
+        form.insert(
+            "attachment",
+            Field::File(FormFile {
+                filename: String::from("big.pdf"),
+                content_type: String::from("application/pdf"),
+                bytes: b"0123456789"[..].into(),
+                headers: HeaderMap::new(),
+            }),
+        );
+
+        let errors = get_definition().validate(&form).unwrap_err();
+        assert_eq!(errors.get("name"), Some(&FieldError::TooLong(20)));
+        assert_eq!(errors.get("email"), Some(&FieldError::InvalidEmail));
+        assert_eq!(errors.get("age"), Some(&FieldError::TooSmall(18.0)));
+        assert_eq!(errors.get("topic"), Some(&FieldError::InvalidOption));
+        assert_eq!(
+            errors.get("attachment"),
+            Some(&FieldError::TypeNotAllowed(String::from("application/pdf")))
+        );
+
+        let errors = get_definition().validate(&Form::new()).unwrap_err();
+        assert_eq!(errors.get("name"), Some(&FieldError::Required));
+        assert_eq!(errors.get("age"), None);
+    }
+
+    #[test]
+    fn blank_text_is_missing() {
+        let mut form = get_form();
+        form.insert("name", Field::Text(String::from("   ")));
+        form.insert("age", Field::Text(String::new()));
+        let errors = get_definition().validate(&form).unwrap_err();
+        assert_eq!(errors.iter().count(), 1);
+        assert_eq!(errors.get("name"), Some(&FieldError::Required));
+    }
+
+    #[test]
+    fn render_empty_form() {
+        let html = get_definition().render(None, None);
+        assert!(html.starts_with(
+            "<form method=\"post\" action=\"/contact\" enctype=\"multipart/form-data\">"
+        ));
+        assert!(html.contains("<label for=\"field-name\">Your name</label>"));
+        assert!(html.contains(
+            "<input type=\"text\" id=\"field-name\" name=\"name\" required maxlength=\"20\">"
+        ));
+        assert!(html.contains("<label for=\"field-age\">age</label>"));
+        assert!(html.contains("min=\"18\" max=\"120\""));
+        assert!(html.contains("<option value=\"support\">Support &amp; help</option>"));
+        assert!(html.contains("accept=\"image/*\""));
+        assert!(html.ends_with("<button type=\"submit\">Submit</button>\n</form>\n"));
+    }
+
+    #[test]
+    fn render_preserves_values_and_errors() {
+        let mut form = get_form();
+        form.insert("name", Field::Text(String::from("<Jane>")));
+        form.insert("email", Field::Text(String::from("nope")));
+        let errors = get_definition().validate(&form).unwrap_err();
+        let html = get_definition().render(Some(&form), Some(&errors));
+
+        assert!(html.contains("name=\"name\" value=\"&lt;Jane&gt;\""));
+        assert!(html.contains("<option value=\"support\" selected>"));
+        assert!(html.contains(
+            "name=\"email\" value=\"nope\" required aria-invalid=\"true\" aria-describedby=\"field-email-error\">"
+        ));
+        assert!(html.contains(
+            "<p id=\"field-email-error\" class=\"error\">Enter a valid email address.</p>"
+        ));
+    }
+}
+
+/// The kind of control used for a field, which also determines how its value
+/// is validated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "definition-files",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum FieldType {
+    Text,
+    TextArea,
+    Email,
+    Url,
+    Tel,
+    Number,
+    Password,
+    Hidden,
+    Checkbox,
+    Select,
+    File,
+}
+
+impl FieldType {
+    /// Returns the `type` attribute used for an `<input>` of this type.
+    fn input_type(self) -> &'static str {
+        match self {
+            FieldType::Text | FieldType::TextArea | FieldType::Select => "text",
+            FieldType::Email => "email",
+            FieldType::Url => "url",
+            FieldType::Tel => "tel",
+            FieldType::Number => "number",
+            FieldType::Password => "password",
+            FieldType::Hidden => "hidden",
+            FieldType::Checkbox => "checkbox",
+            FieldType::File => "file",
+        }
+    }
+}
+
+/// A single choice of a `Select` field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectOption {
+    /// The value submitted when this option is chosen.
+    pub value: String,
+    /// The text shown to the user.
+    pub label: String,
+}
+
+/// The definition of a single form field: its type, label, and constraints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDefinition {
+    name: String,
+    kind: FieldType,
+    label: Option<String>,
+    help: Option<String>,
+    required: bool,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min: Option<f64>,
+    max: Option<f64>,
+    options: Vec<SelectOption>,
+    accept: MimeAllowList,
+    max_size: Option<usize>,
+}
+
+impl FieldDefinition {
+    /// Creates an optional field with no constraints, labelled with its name.
+    pub fn new(name: &str, kind: FieldType) -> Self {
+        FieldDefinition {
+            name: name.to_string(),
+            kind,
+            label: None,
+            help: None,
+            required: false,
+            min_length: None,
+            max_length: None,
+            min: None,
+            max: None,
+            options: Vec::new(),
+            accept: MimeAllowList::new(),
+            max_size: None,
+        }
+    }
+
+    /// Returns the name of the field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the field.
+    pub fn kind(&self) -> FieldType {
+        self.kind
+    }
+
+    /// Returns the label shown for the field, which defaults to its name.
+    pub fn get_label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Sets the label shown for the field.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Sets help text shown below the field.
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// Marks the field as required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Sets the minimum number of characters of a text value.
+    pub fn min_length(mut self, len: usize) -> Self {
+        self.min_length = Some(len);
+        self
+    }
+
+    /// Sets the maximum number of characters of a text value.
+    pub fn max_length(mut self, len: usize) -> Self {
+        self.max_length = Some(len);
+        self
+    }
+
+    /// Sets the smallest value allowed in a `Number` field.
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Sets the largest value allowed in a `Number` field.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Adds a choice to a `Select` field.
+    pub fn option(mut self, value: &str, label: &str) -> Self {
+        self.options.push(SelectOption {
+            value: value.to_string(),
+            label: label.to_string(),
+        });
+        self
+    }
+
+    /// Adds a content type pattern accepted by a `File` field. If no
+    /// patterns are added, any content type is accepted.
+    pub fn accept(mut self, pattern: &str) -> Self {
+        self.accept = self.accept.allow(pattern);
+        self
+    }
+
+    /// Sets the largest file, in bytes, accepted by a `File` field.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Checks a submitted value against this definition. `None` means the
+    /// field was not submitted.
+    pub fn validate(&self, field: Option<&Field>) -> Result<(), FieldError> {
+        let field = match field {
+            Some(Field::Text(text)) if text.trim().is_empty() => None,
+            other => other,
+        };
+        let field = match field {
+            Some(field) => field,
+            None if self.required => return Err(FieldError::Required),
+            None => return Ok(()),
+        };
+
+        match (self.kind, field) {
+            (FieldType::File, Field::File(file)) => {
+                if !self.accept.patterns().is_empty() && !file.is_allowed(&self.accept) {
+                    return Err(FieldError::TypeNotAllowed(file.content_type.clone()));
+                }
+                match self.max_size {
+                    Some(max) if file.bytes.len() > max => Err(FieldError::FileTooLarge(max)),
+                    _ => Ok(()),
+                }
+            }
+            (FieldType::File, Field::Text(_)) => Err(FieldError::NotFile),
+            (_, Field::File(_)) => Err(FieldError::NotText),
+            (_, Field::Text(text)) => self.validate_text(text),
+        }
+    }
+
+    fn validate_text(&self, text: &str) -> Result<(), FieldError> {
+        let len = text.chars().count();
+        if let Some(min) = self.min_length.filter(|min| len < *min) {
+            return Err(FieldError::TooShort(min));
+        }
+        if let Some(max) = self.max_length.filter(|max| len > *max) {
+            return Err(FieldError::TooLong(max));
+        }
+
+        match self.kind {
+            FieldType::Email if !is_email(text) => Err(FieldError::InvalidEmail),
+            FieldType::Url if !is_url(text) => Err(FieldError::InvalidUrl),
+            FieldType::Select if !self.options.iter().any(|opt| opt.value == text) => {
+                Err(FieldError::InvalidOption)
+            }
+            FieldType::Number => {
+                let value: f64 = text
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|value: &f64| value.is_finite())
+                    .ok_or(FieldError::NotANumber)?;
+                if let Some(min) = self.min.filter(|min| value < *min) {
+                    return Err(FieldError::TooSmall(min));
+                }
+                match self.max {
+                    Some(max) if value > max => Err(FieldError::TooLarge(max)),
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the HTML for this field, wrapped in a `<div class="field">`.
+    fn render(&self, out: &mut String, value: Option<&str>, error: Option<&FieldError>) {
+        let id = format!("field-{}", escape(&self.name));
+        let name = escape(&self.name);
+
+        let mut described_by = Vec::new();
+        if self.help.is_some() {
+            described_by.push(format!("{}-help", id));
+        }
+        if error.is_some() {
+            described_by.push(format!("{}-error", id));
+        }
+
+        let mut attrs = String::new();
+        if self.required {
+            attrs.push_str(" required");
+        }
+        if let Some(len) = self.min_length {
+            write!(attrs, " minlength=\"{}\"", len).unwrap();
+        }
+        if let Some(len) = self.max_length {
+            write!(attrs, " maxlength=\"{}\"", len).unwrap();
+        }
+        if self.kind == FieldType::Number {
+            if let Some(min) = self.min {
+                write!(attrs, " min=\"{}\"", min).unwrap();
+            }
+            if let Some(max) = self.max {
+                write!(attrs, " max=\"{}\"", max).unwrap();
+            }
+        }
+        if self.kind == FieldType::File && !self.accept.patterns().is_empty() {
+            write!(
+                attrs,
+                " accept=\"{}\"",
+                escape(&self.accept.patterns().join(","))
+            )
+            .unwrap();
+        }
+        if error.is_some() {
+            attrs.push_str(" aria-invalid=\"true\"");
+        }
+        if !described_by.is_empty() {
+            write!(attrs, " aria-describedby=\"{}\"", described_by.join(" ")).unwrap();
+        }
+
+        if self.kind == FieldType::Hidden {
+            let value = escape(value.unwrap_or_default());
+            writeln!(
+                out,
+                "<input type=\"hidden\" id=\"{}\" name=\"{}\" value=\"{}\">",
+                id, name, value
+            )
+            .unwrap();
+            return;
+        }
+
+        out.push_str("<div class=\"field\">\n");
+        let label = format!(
+            "<label for=\"{}\">{}</label>\n",
+            id,
+            escape(self.get_label())
+        );
+        if self.kind != FieldType::Checkbox {
+            out.push_str(&label);
+        }
+
+        match self.kind {
+            FieldType::TextArea => writeln!(
+                out,
+                "<textarea id=\"{}\" name=\"{}\"{}>{}</textarea>",
+                id,
+                name,
+                attrs,
+                escape(value.unwrap_or_default())
+            ),
+            FieldType::Select => {
+                writeln!(out, "<select id=\"{}\" name=\"{}\"{}>", id, name, attrs).unwrap();
+                out.push_str("<option value=\"\"></option>\n");
+                for opt in &self.options {
+                    let selected = if value == Some(opt.value.as_str()) {
+                        " selected"
+                    } else {
+                        ""
+                    };
+                    writeln!(
+                        out,
+                        "<option value=\"{}\"{}>{}</option>",
+                        escape(&opt.value),
+                        selected,
+                        escape(&opt.label)
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "</select>")
+            }
+            FieldType::Checkbox => {
+                let checked = if value.is_some() { " checked" } else { "" };
+                writeln!(
+                    out,
+                    "<input type=\"checkbox\" id=\"{}\" name=\"{}\" value=\"on\"{}{}>",
+                    id, name, checked, attrs
+                )
+                .unwrap();
+                out.push_str(&label);
+                Ok(())
+            }
+            // Submitted files and passwords are never sent back to the browser
+            FieldType::File | FieldType::Password => writeln!(
+                out,
+                "<input type=\"{}\" id=\"{}\" name=\"{}\"{}>",
+                self.kind.input_type(),
+                id,
+                name,
+                attrs
+            ),
+            _ => {
+                let value = match value {
+                    Some(value) => format!(" value=\"{}\"", escape(value)),
+                    None => String::new(),
+                };
+                writeln!(
+                    out,
+                    "<input type=\"{}\" id=\"{}\" name=\"{}\"{}{}>",
+                    self.kind.input_type(),
+                    id,
+                    name,
+                    value,
+                    attrs
+                )
+            }
+        }
+        .unwrap();
+
+        if let Some(help) = &self.help {
+            writeln!(
+                out,
+                "<p id=\"{}-help\" class=\"help\">{}</p>",
+                id,
+                escape(help)
+            )
+            .unwrap();
+        }
+        if let Some(error) = error {
+            writeln!(
+                out,
+                "<p id=\"{}-error\" class=\"error\">{}</p>",
+                id,
+                escape(&error.to_string())
+            )
+            .unwrap();
+        }
+        out.push_str("</div>\n");
+    }
+}
+
+/// The reasons a single field can fail validation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldError {
+    Required,
+    TooShort(usize),
+    TooLong(usize),
+    NotANumber,
+    TooSmall(f64),
+    TooLarge(f64),
+    InvalidEmail,
+    InvalidUrl,
+    InvalidOption,
+    NotText,
+    NotFile,
+    FileTooLarge(usize),
+    TypeNotAllowed(String),
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FieldError::Required => write!(f, "This field is required."),
+            FieldError::TooShort(min) => write!(f, "Enter at least {} characters.", min),
+            FieldError::TooLong(max) => write!(f, "Enter no more than {} characters.", max),
+            FieldError::NotANumber => write!(f, "Enter a number."),
+            FieldError::TooSmall(min) => write!(f, "Enter a number no smaller than {}.", min),
+            FieldError::TooLarge(max) => write!(f, "Enter a number no larger than {}.", max),
+            FieldError::InvalidEmail => write!(f, "Enter a valid email address."),
+            FieldError::InvalidUrl => write!(f, "Enter a valid URL."),
+            FieldError::InvalidOption => write!(f, "Choose one of the available options."),
+            FieldError::NotText => write!(f, "This field must not be a file."),
+            FieldError::NotFile => write!(f, "This field must be a file."),
+            FieldError::FileTooLarge(max) => {
+                write!(f, "Files must be no larger than {} bytes.", max)
+            }
+            FieldError::TypeNotAllowed(content_type) => {
+                write!(f, "Files of type {} are not allowed.", content_type)
+            }
+        }
+    }
+}
+
+/// The fields of a `Form` that failed validation, in definition order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationErrors(Vec<(String, FieldError)>);
+
+impl ValidationErrors {
+    /// Returns the error for the named field, if it failed validation.
+    pub fn get(&self, name: &str) -> Option<&FieldError> {
+        self.0
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, err)| err)
+    }
+
+    /// Iterates over the names of the failed fields and their errors.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FieldError)> {
+        self.0.iter().map(|(name, err)| (name.as_str(), err))
+    }
+
+    /// Indicates whether no fields failed validation.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let errors: Vec<String> = self
+            .iter()
+            .map(|(name, err)| format!("{}: {}", name, err))
+            .collect();
+        write!(f, "{}", errors.join(" "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[cfg(feature = "server-warp")]
+impl From<ValidationErrors> for Status<String> {
+    fn from(errors: ValidationErrors) -> Self {
+        Status::with_message(StatusCode::UNPROCESSABLE_ENTITY, errors.to_string())
+    }
+}
+
+/// A form's fields, from which both its HTML and its server-side validation
+/// are derived.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormDefinition {
+    action: Option<String>,
+    method: String,
+    submit_label: String,
+    fields: Vec<FieldDefinition>,
+}
+
+impl Default for FormDefinition {
+    fn default() -> Self {
+        FormDefinition {
+            action: None,
+            method: String::from("post"),
+            submit_label: String::from("Submit"),
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl FormDefinition {
+    /// Creates a definition with no fields that is submitted with `POST` to
+    /// the current URL.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the URL the form is submitted to.
+    pub fn action(mut self, action: &str) -> Self {
+        self.action = Some(action.to_string());
+        self
+    }
+
+    /// Sets the HTTP method used to submit the form.
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = method.to_lowercase();
+        self
+    }
+
+    /// Sets the text of the submit button.
+    pub fn submit_label(mut self, label: &str) -> Self {
+        self.submit_label = label.to_string();
+        self
+    }
+
+    /// Adds a field. Fields are rendered and validated in the order they are
+    /// added.
+    pub fn field(mut self, field: FieldDefinition) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Returns the defined fields, in order.
+    pub fn fields(&self) -> &[FieldDefinition] {
+        &self.fields
+    }
+
+    /// Validates `form` against every defined field. Fields in `form` that
+    /// are not defined are ignored.
+    pub fn validate(&self, form: &Form) -> Result<(), ValidationErrors> {
+        let errors: Vec<_> = self
+            .fields
+            .iter()
+            .filter_map(|def| {
+                def.validate(form.get(&def.name))
+                    .err()
+                    .map(|err| (def.name.clone(), err))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+
+    /// Renders the form as HTML. Each field is labelled, and the values of
+    /// `submitted` (other than files and passwords) are filled back in along
+    /// with any `errors`, so that a rejected form can be shown again.
+    pub fn render(&self, submitted: Option<&Form>, errors: Option<&ValidationErrors>) -> String {
+        let mut out = String::new();
+        write!(out, "<form method=\"{}\"", escape(&self.method)).unwrap();
+        if let Some(action) = &self.action {
+            write!(out, " action=\"{}\"", escape(action)).unwrap();
+        }
+        if self.fields.iter().any(|def| def.kind == FieldType::File) {
+            out.push_str(" enctype=\"multipart/form-data\"");
+        }
+        out.push_str(">\n");
+
+        for def in &self.fields {
+            let value = submitted
+                .and_then(|form| form.get(&def.name))
+                .and_then(Field::as_text);
+            let error = errors.and_then(|errors| errors.get(&def.name));
+            def.render(&mut out, value, error);
+        }
+
+        writeln!(
+            out,
+            "<button type=\"submit\">{}</button>\n</form>",
+            escape(&self.submit_label)
+        )
+        .unwrap();
+        out
+    }
+}
+
+/// Escapes text for use in HTML content and quoted attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A deliberately loose check that `text` looks like `local@domain.tld`.
+fn is_email(text: &str) -> bool {
+    let mut parts = text.splitn(2, '@');
+    match (parts.next(), parts.next()) {
+        (Some(local), Some(domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !text.contains(char::is_whitespace)
+        }
+        _ => false,
+    }
+}
+
+/// Checks that `text` is an absolute `http` or `https` URL with a host.
+fn is_url(text: &str) -> bool {
+    let rest = text
+        .strip_prefix("https://")
+        .or_else(|| text.strip_prefix("http://"));
+    match rest {
+        Some(rest) => {
+            !rest.starts_with('/') && !rest.is_empty() && !text.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
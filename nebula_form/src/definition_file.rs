@@ -0,0 +1,507 @@
+use crate::definition::{FieldDefinition, FieldType, FormDefinition};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Field, Form};
+
+    const TOML: &str = r#"
+action = "/contact"
+submit_label = "Send"
+
+[[fields]]
+name = "email"
+type = "email"
+label = "Email address"
+required = true
+max_length = 100
+
+[[fields]]
+name = "topic"
+type = "select"
+options = [
+    { value = "sales", label = "Sales" },
+    { value = "support", label = "Support" },
+]
+
+[[fields]]
+name = "attachment"
+type = "file"
+accept = ["image/*", "application/pdf"]
+max_size = 1048576
+"#;
+
+    const YAML: &str = r#"
+action: /contact
+submit_label: Send
+fields:
+  - name: email
+    type: email
+    label: Email address
+    required: true
+    max_length: 100
+  - name: topic
+    type: select
+    options:
+      - value: sales
+        label: Sales
+      - value: support
+        label: Support
+  - name: attachment
+    type: file
+    accept: ["image/*", application/pdf]
+    max_size: 1048576
+"#;
+
+    fn get_definition() -> FormDefinition {
+        FormDefinition::new()
+            .action("/contact")
+            .submit_label("Send")
+            .field(
+                FieldDefinition::new("email", FieldType::Email)
+                    .label("Email address")
+                    .required()
+                    .max_length(100),
+            )
+            .field(
+                FieldDefinition::new("topic", FieldType::Select)
+                    .option("sales", "Sales")
+                    .option("support", "Support"),
+            )
+            .field(
+                FieldDefinition::new("attachment", FieldType::File)
+                    .accept("image/*")
+                    .accept("application/pdf")
+                    .max_size(1048576),
+            )
+    }
+
+    fn assert_position(err: LoadError, line: usize, needle: &str) {
+        match &err {
+            LoadError::Parse {
+                line: Some(err_line),
+                message,
+                ..
+            } => {
+                assert_eq!(*err_line, line, "{}", err);
+                assert!(message.contains(needle), "{}", err);
+            }
+            other => panic!("Unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn load_toml_and_yaml() {
+        assert_eq!(
+            FormDefinition::from_toml_str(TOML).unwrap(),
+            get_definition()
+        );
+        assert_eq!(
+            FormDefinition::from_yaml_str(YAML).unwrap(),
+            get_definition()
+        );
+    }
+
+    #[test]
+    fn loaded_definition_validates() {
+        let def = FormDefinition::from_toml_str(TOML).unwrap();
+        let mut form = Form::new();
+        form.insert("topic", Field::Text(String::from("sales")));
+        assert!(def.validate(&form).is_err());
+        form.insert("email", Field::Text(String::from("jane@example.com")));
+        assert_eq!(def.validate(&form), Ok(()));
+    }
+
+    #[test]
+    fn errors_have_positions() {
+        let toml = TOML.replace("type = \"email\"", "type = \"emial\"");
+        assert_position(
+            FormDefinition::from_toml_str(&toml).unwrap_err(),
+            7,
+            "emial",
+        );
+
+        let yaml = YAML.replace("type: email", "type: emial");
+        assert_position(
+            FormDefinition::from_yaml_str(&yaml).unwrap_err(),
+            6,
+            "emial",
+        );
+
+        let toml = TOML.replace("max_size = 1048576", "max_size = 1048576\nmax_sise = 1");
+        assert_position(
+            FormDefinition::from_toml_str(&toml).unwrap_err(),
+            25,
+            "max_sise",
+        );
+    }
+
+    #[test]
+    fn inconsistent_fields_are_rejected() {
+        let toml = TOML.replace("name = \"topic\"", "name = \"email\"");
+        match FormDefinition::from_toml_str(&toml).unwrap_err() {
+            LoadError::Parse { message, .. } => assert!(message.contains("more than once")),
+            other => panic!("Unexpected error: {}", other),
+        }
+
+        let yaml = YAML.replace("    max_length: 100", "    max_size: 100");
+        match FormDefinition::from_yaml_str(&yaml).unwrap_err() {
+            LoadError::Parse { message, .. } => assert!(message.contains("only applies")),
+            other => panic!("Unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn load_file_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("contact.yml");
+        fs::write(&path, YAML.replace("type: email", "type: emial")).unwrap();
+
+        let err = FormDefinition::from_file(&path).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{}:6:", path.display())));
+
+        let path = dir.path().join("contact.ini");
+        fs::write(&path, "").unwrap();
+        match FormDefinition::from_file(&path) {
+            Err(LoadError::UnknownFormat(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
+
+/// Errors that can occur while loading a `FormDefinition` from a file.
+///
+/// Requires `features = "definition-files"`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read.
+    Io(PathBuf, io::Error),
+    /// The file's extension is not `.toml`, `.yaml`, or `.yml`.
+    UnknownFormat(PathBuf),
+    /// The definition could not be parsed or is inconsistent. Lines and
+    /// columns start at 1.
+    Parse {
+        file: Option<PathBuf>,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+}
+
+impl LoadError {
+    fn with_file(self, path: &Path) -> Self {
+        match self {
+            LoadError::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => LoadError::Parse {
+                file: Some(path.to_path_buf()),
+                line,
+                column,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(path, err) => write!(f, "Could not read {}: {}", path.display(), err),
+            LoadError::UnknownFormat(path) => write!(
+                f,
+                "Unknown form definition format: {} (expected .toml, .yaml, or .yml)",
+                path.display()
+            ),
+            LoadError::Parse {
+                file,
+                line,
+                column,
+                message,
+            } => {
+                match file {
+                    Some(file) => write!(f, "{}", file.display())?,
+                    None => write!(f, "<string>")?,
+                }
+                if let Some(line) = line {
+                    write!(f, ":{}", line)?;
+                    if let Some(column) = column {
+                        write!(f, ":{}", column)?;
+                    }
+                }
+                write!(f, ": {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<toml::de::Error> for LoadError {
+    fn from(err: toml::de::Error) -> Self {
+        // The span is only a byte range, so the position is filled in by
+        // `from_toml_str`, which has the source
+        LoadError::Parse {
+            file: None,
+            line: None,
+            column: None,
+            message: err.message().to_string(),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for LoadError {
+    fn from(err: serde_yaml::Error) -> Self {
+        let location = err.location();
+        let message = err.to_string();
+        // serde_yaml appends the position to the message, which is shown
+        // separately instead
+        let message = match message.rfind(" at line ") {
+            Some(idx) if location.is_some() => message[..idx].to_string(),
+            _ => message,
+        };
+        LoadError::Parse {
+            file: None,
+            line: location.as_ref().map(|loc| loc.line()),
+            column: location.as_ref().map(|loc| loc.column()),
+            message,
+        }
+    }
+}
+
+/// Returns the 1-based line and column of the byte `offset` in `source`.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |idx| idx + 1) + 1;
+    (line, column)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOption {
+    value: String,
+    label: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawField {
+    name: String,
+    #[serde(rename = "type")]
+    kind: FieldType,
+    label: Option<String>,
+    help: Option<String>,
+    #[serde(default)]
+    required: bool,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min: Option<f64>,
+    max: Option<f64>,
+    #[serde(default)]
+    options: Vec<RawOption>,
+    #[serde(default)]
+    accept: Vec<String>,
+    max_size: Option<usize>,
+}
+
+impl TryFrom<RawField> for FieldDefinition {
+    type Error = String;
+
+    fn try_from(raw: RawField) -> Result<Self, Self::Error> {
+        let kind = raw.kind;
+        let only_for = |set: bool, allowed: bool, key: &str, kinds: &str| {
+            if set && !allowed {
+                Err(format!(
+                    "field {}: `{}` only applies to {} fields",
+                    raw.name, key, kinds
+                ))
+            } else {
+                Ok(())
+            }
+        };
+
+        if raw.name.is_empty() {
+            return Err(String::from("field names must not be empty"));
+        }
+        let is_file = kind == FieldType::File;
+        let is_number = kind == FieldType::Number;
+        let is_select = kind == FieldType::Select;
+        only_for(!raw.accept.is_empty(), is_file, "accept", "file")?;
+        only_for(raw.max_size.is_some(), is_file, "max_size", "file")?;
+        only_for(raw.min.is_some(), is_number, "min", "number")?;
+        only_for(raw.max.is_some(), is_number, "max", "number")?;
+        only_for(!raw.options.is_empty(), is_select, "options", "select")?;
+        only_for(
+            raw.min_length.is_some() || raw.max_length.is_some(),
+            !is_file,
+            "min_length and max_length",
+            "non-file",
+        )?;
+        if is_select && raw.options.is_empty() {
+            return Err(format!("field {}: select fields need options", raw.name));
+        }
+        if let (Some(min), Some(max)) = (raw.min_length, raw.max_length) {
+            if min > max {
+                return Err(format!(
+                    "field {}: min_length is larger than max_length",
+                    raw.name
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (raw.min, raw.max) {
+            if min > max {
+                return Err(format!("field {}: min is larger than max", raw.name));
+            }
+        }
+
+        let mut def = FieldDefinition::new(&raw.name, kind);
+        if let Some(label) = &raw.label {
+            def = def.label(label);
+        }
+        if let Some(help) = &raw.help {
+            def = def.help(help);
+        }
+        if raw.required {
+            def = def.required();
+        }
+        if let Some(len) = raw.min_length {
+            def = def.min_length(len);
+        }
+        if let Some(len) = raw.max_length {
+            def = def.max_length(len);
+        }
+        if let Some(min) = raw.min {
+            def = def.min(min);
+        }
+        if let Some(max) = raw.max {
+            def = def.max(max);
+        }
+        for opt in &raw.options {
+            def = def.option(&opt.value, opt.label.as_deref().unwrap_or(&opt.value));
+        }
+        for pattern in &raw.accept {
+            def = def.accept(pattern);
+        }
+        if let Some(size) = raw.max_size {
+            def = def.max_size(size);
+        }
+        Ok(def)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawForm {
+    action: Option<String>,
+    method: Option<String>,
+    submit_label: Option<String>,
+    fields: Vec<RawField>,
+}
+
+impl TryFrom<RawForm> for FormDefinition {
+    type Error = String;
+
+    fn try_from(raw: RawForm) -> Result<Self, Self::Error> {
+        let mut def = FormDefinition::new();
+        if let Some(action) = &raw.action {
+            def = def.action(action);
+        }
+        if let Some(method) = &raw.method {
+            def = def.method(method);
+        }
+        if let Some(label) = &raw.submit_label {
+            def = def.submit_label(label);
+        }
+
+        let mut names = HashSet::new();
+        for field in raw.fields {
+            if !names.insert(field.name.clone()) {
+                return Err(format!("field {} is defined more than once", field.name));
+            }
+            def = def.field(FieldDefinition::try_from(field)?);
+        }
+        Ok(def)
+    }
+}
+
+/// Deserializes a `RawForm` and converts it, reporting conversion errors
+/// through the deserializer so that they carry its position.
+fn deserialize_form<'de, D>(deserializer: D) -> Result<FormDefinition, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = RawForm::deserialize(deserializer)?;
+    FormDefinition::try_from(raw).map_err(serde::de::Error::custom)
+}
+
+impl FormDefinition {
+    /// Loads a definition from TOML.
+    ///
+    /// The document has optional top-level `action`, `method`, and
+    /// `submit_label` keys and a `fields` array. Each field has a `name`, a
+    /// `type` (`text`, `textarea`, `email`, `url`, `tel`, `number`,
+    /// `password`, `hidden`, `checkbox`, `select`, or `file`) and,
+    /// optionally, `label`, `help`, `required`, `min_length`, `max_length`,
+    /// `min`, `max`, `options` (a list of `value`/`label` tables), `accept`
+    /// (a list of content types), and `max_size`.
+    ///
+    /// Requires `features = "definition-files"`.
+    pub fn from_toml_str(source: &str) -> Result<Self, LoadError> {
+        let deserializer = toml::Deserializer::new(source);
+        deserialize_form(deserializer).map_err(|err| {
+            let position = err.span().map(|span| line_column(source, span.start));
+            match LoadError::from(err) {
+                LoadError::Parse { file, message, .. } => LoadError::Parse {
+                    file,
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    message,
+                },
+                other => other,
+            }
+        })
+    }
+
+    /// Loads a definition from YAML with the same structure as
+    /// `from_toml_str`.
+    ///
+    /// Requires `features = "definition-files"`.
+    pub fn from_yaml_str(source: &str) -> Result<Self, LoadError> {
+        let deserializer = serde_yaml::Deserializer::from_str(source);
+        deserialize_form(deserializer).map_err(LoadError::from)
+    }
+
+    /// Loads a definition from a `.toml`, `.yaml`, or `.yml` file. Parse
+    /// errors include the path of the file.
+    ///
+    /// Requires `features = "definition-files"`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        let load = match extension.as_deref() {
+            Some("toml") => Self::from_toml_str,
+            Some("yaml") | Some("yml") => Self::from_yaml_str,
+            _ => return Err(LoadError::UnknownFormat(path.to_path_buf())),
+        };
+
+        let source =
+            fs::read_to_string(path).map_err(|err| LoadError::Io(path.to_path_buf(), err))?;
+        load(&source).map_err(|err| err.with_file(path))
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{Field, Form, FormFile};
+use sha2::{Digest, Sha256};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeaderMap;
+    use bytes::Bytes;
+
+    fn get_file(content: &'static [u8]) -> Field {
+        Field::File(FormFile {
+            filename: String::from("file.txt"),
+            content_type: String::from("text/plain"),
+            bytes: Bytes::from_static(content),
+            headers: HeaderMap::new(),
+        })
+    }
+
+    fn get_form() -> Form {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("Jane")));
+        form.insert("step", Field::Text(String::from("1")));
+        form.insert("upload", get_file(b"first version"));
+        form
+    }
+
+    #[test]
+    fn identical_forms_have_empty_diff() {
+        let diff = get_form().diff(&get_form());
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn diff_added_removed_changed() {
+        let old = get_form();
+        let mut new = get_form();
+        new.remove("step");
+        new.insert("name", Field::Text(String::from("Janet")));
+        new.insert("email", Field::Text(String::from("jane@example.com")));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![String::from("email")]);
+        assert_eq!(diff.removed, vec![String::from("step")]);
+        assert_eq!(diff.changed, vec![String::from("name")]);
+    }
+
+    #[test]
+    fn diff_detects_file_content_change() {
+        let old = get_form();
+        let mut new = get_form();
+        new.insert("upload", get_file(b"second version"));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.changed, vec![String::from("upload")]);
+    }
+
+    #[test]
+    fn diff_text_replaced_by_file_is_changed() {
+        let old = get_form();
+        let mut new = get_form();
+        new.insert("name", get_file(b"Jane"));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.changed, vec![String::from("name")]);
+    }
+
+    #[test]
+    fn file_sha256() {
+        let file = get_file(b"abc").into_file().unwrap();
+        assert_eq!(
+            file.sha256_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    fn get_update() -> Form {
+        let mut update = Form::new();
+        update.insert("name", Field::Text(String::from("Doe")));
+        update.insert("email", Field::Text(String::from("jane@example.com")));
+        update.insert("upload", get_file(b"second version"));
+        update
+    }
+
+    #[test]
+    fn merge_overwrite() {
+        let mut form = get_form();
+        form.merge(get_update(), &MergeStrategy::Overwrite);
+
+        assert_eq!(form.get("name").and_then(Field::as_text), Some("Doe"));
+        assert_eq!(form.get("step").and_then(Field::as_text), Some("1"));
+        assert_eq!(form.get("upload"), Some(&get_file(b"second version")));
+        assert_eq!(form.len(), 4);
+    }
+
+    #[test]
+    fn merge_keep_existing() {
+        let mut form = get_form();
+        form.merge(get_update(), &MergeStrategy::KeepExisting);
+
+        assert_eq!(form.get("name").and_then(Field::as_text), Some("Jane"));
+        assert_eq!(
+            form.get("email").and_then(Field::as_text),
+            Some("jane@example.com")
+        );
+        assert_eq!(form.get("upload"), Some(&get_file(b"first version")));
+        assert_eq!(form.len(), 4);
+    }
+
+    #[test]
+    fn merge_append_values() {
+        let mut form = get_form();
+        form.merge(get_update(), &MergeStrategy::Append(String::from(", ")));
+
+        assert_eq!(form.get("name").and_then(Field::as_text), Some("Jane, Doe"));
+        assert_eq!(
+            form.get("email").and_then(Field::as_text),
+            Some("jane@example.com")
+        );
+        assert_eq!(form.get("upload"), Some(&get_file(b"second version")));
+    }
+}
+
+/// The structural differences between two `Form`s, as returned by
+/// `Form::diff`.
+///
+/// Each list contains field names sorted in ascending order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FormDiff {
+    /// Fields that only exist in the newer form.
+    pub added: Vec<String>,
+    /// Fields that only exist in the older form.
+    pub removed: Vec<String>,
+    /// Fields that exist in both forms with different contents.
+    pub changed: Vec<String>,
+}
+
+impl FormDiff {
+    /// Indicates whether the two forms had no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Determines how `Form::merge` handles fields that exist in both forms.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The incoming field replaces the existing one.
+    Overwrite,
+    /// The existing field is kept and the incoming one is discarded.
+    KeepExisting,
+    /// Text values are joined, existing value first, with the given
+    /// separator. If either field is a file, the incoming field replaces the
+    /// existing one.
+    Append(String),
+}
+
+impl FormFile {
+    /// Returns the SHA-256 hash of the file's content.
+    pub fn sha256(&self) -> [u8; 32] {
+        Sha256::digest(&self.bytes).into()
+    }
+
+    /// Returns the SHA-256 hash of the file's content as lowercase
+    /// hexadecimal.
+    pub fn sha256_hex(&self) -> String {
+        self.sha256().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Indicates whether two fields hold the same value. Files are compared by
+/// name, content type, and the hash of their content.
+fn same_field(old: &Field, new: &Field) -> bool {
+    match (old, new) {
+        (Field::Text(old), Field::Text(new)) => old == new,
+        (Field::File(old), Field::File(new)) => {
+            old.filename == new.filename
+                && old.content_type == new.content_type
+                && old.sha256() == new.sha256()
+        }
+        _ => false,
+    }
+}
+
+impl Form {
+    /// Compares this `Form` to a newer version of it and returns the fields
+    /// that were added, removed, or changed.
+    pub fn diff(&self, newer: &Form) -> FormDiff {
+        let mut diff = FormDiff::default();
+
+        for (name, old) in self.iter() {
+            match newer.get(name) {
+                None => diff.removed.push(name.clone()),
+                Some(new) if !same_field(old, new) => diff.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+
+        diff.added = newer
+            .iter()
+            .filter(|(name, _)| !self.contains_field(name))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        diff.added.sort_unstable();
+        diff.removed.sort_unstable();
+        diff.changed.sort_unstable();
+        diff
+    }
+
+    /// Adds every field of `other` to this `Form`. Fields that only exist in
+    /// `other` are always added; `strategy` decides what happens to fields
+    /// that exist in both.
+    pub fn merge(&mut self, other: Form, strategy: &MergeStrategy) {
+        for (name, field) in other {
+            let existing = match self.0.get_mut(&name) {
+                None => {
+                    self.0.insert(name, field);
+                    continue;
+                }
+                Some(existing) => existing,
+            };
+
+            match strategy {
+                MergeStrategy::Overwrite => *existing = field,
+                MergeStrategy::KeepExisting => {}
+                MergeStrategy::Append(sep) => match (existing, field) {
+                    (Field::Text(txt), Field::Text(new)) => {
+                        txt.push_str(sep);
+                        txt.push_str(&new);
+                    }
+                    (existing, field) => *existing = field,
+                },
+            }
+        }
+    }
+}
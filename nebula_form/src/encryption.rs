@@ -0,0 +1,534 @@
+use crate::{Field, Form, FormFile, HeaderMap};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use http::header::{HeaderName, HeaderValue};
+use rand::RngCore;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    const KEY: [u8; KEY_LEN] = [7; KEY_LEN];
+    const OLD_KEY: [u8; KEY_LEN] = [3; KEY_LEN];
+
+    fn get_keys() -> Keyring {
+        Keyring::new("2024-01", &KEY).unwrap()
+    }
+
+    fn get_form() -> Form {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("Jane Doe")));
+        form.insert("phone", Field::Text(String::from("+1 555 0100")));
+        form.insert(
+            "passport",
+            Field::File(FormFile {
+                filename: String::from("passport.jpg"),
+                content_type: String::from("image/jpeg"),
+                bytes: Bytes::from_static(b"\xff\xd8\xff\xe0 not really a jpeg"),
+                headers: HeaderMap::new(),
+            }),
+        );
+        form
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_selected_fields() {
+        let keys = get_keys();
+        let mut form = get_form();
+        form.encrypt_fields(&["phone", "passport", "missing"], &keys)
+            .unwrap();
+
+        assert_eq!(form.get("name"), get_form().get("name"));
+        let phone = form.get("phone").unwrap();
+        assert_eq!(phone.encryption_key_id(), Some("2024-01"));
+        assert!(!phone.as_text().unwrap().contains("555"));
+        let passport = form.get("passport").unwrap();
+        assert_eq!(passport.encryption_key_id(), Some("2024-01"));
+        let file = passport.as_file().unwrap();
+        assert_eq!(file.filename, ENCRYPTED_FILENAME);
+        assert!(file.content_type.starts_with(ENCRYPTED_CONTENT_TYPE));
+
+        form.decrypt_fields(&keys).unwrap();
+        assert_eq!(form, get_form());
+    }
+
+    #[test]
+    fn file_names_and_headers_are_encrypted() {
+        let keys = get_keys();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-original-name",
+            HeaderValue::from_bytes(b"passport_J\xf6rg.jpg").unwrap(),
+        );
+        headers.append("content-id", HeaderValue::from_static("<scan>"));
+        let file = FormFile {
+            filename: String::from("passport_JaneDoe.jpg"),
+            content_type: String::from("image/jpeg"),
+            bytes: Bytes::from_static(b"\xff\xd8"),
+            headers,
+        };
+        let mut form = Form::new();
+        form.insert("passport", Field::File(file.clone()));
+
+        form.encrypt_fields(&["passport"], &keys).unwrap();
+        let encrypted = form.get("passport").unwrap().as_file().unwrap();
+        assert_eq!(encrypted.filename, ENCRYPTED_FILENAME);
+        assert!(encrypted.headers.is_empty());
+        assert!(!encrypted.bytes.windows(4).any(|win| win == b"Jane"));
+
+        form.decrypt_fields(&keys).unwrap();
+        assert_eq!(form.get("passport"), Some(&Field::File(file)));
+    }
+
+    #[test]
+    fn encrypting_twice_is_rejected() {
+        let keys = get_keys();
+        let mut form = get_form();
+        form.encrypt_fields(&["phone"], &keys).unwrap();
+        let once = form.clone();
+        assert_eq!(
+            form.encrypt_fields(&["name"], &keys),
+            Err(Error::AlreadyEncrypted(String::from("phone")))
+        );
+        assert_eq!(form, once);
+    }
+
+    #[test]
+    fn submitted_markers_are_rejected() {
+        let keys = get_keys();
+        let forged = format!("{}2024-01:AAAA", TEXT_PREFIX);
+
+        // A named field cannot skip encryption by looking encrypted
+        let mut form = get_form();
+        form.insert("phone", Field::Text(forged.clone()));
+        assert_eq!(
+            form.encrypt_fields(&["phone"], &keys),
+            Err(Error::AlreadyEncrypted(String::from("phone")))
+        );
+
+        // Nor can another field make decrypting the form fail later
+        let mut form = get_form();
+        form.insert("comment", Field::Text(forged));
+        assert_eq!(
+            form.encrypt_fields(&["phone"], &keys),
+            Err(Error::AlreadyEncrypted(String::from("comment")))
+        );
+
+        let mut form = get_form();
+        if let Some(Field::File(file)) = form.remove("passport") {
+            let content_type = format!("{}; key-id=2024-01", ENCRYPTED_CONTENT_TYPE);
+            form.insert(
+                "passport",
+                Field::File(FormFile {
+                    content_type,
+                    ..file
+                }),
+            );
+        }
+        assert_eq!(
+            form.encrypt_fields(&["phone"], &keys),
+            Err(Error::AlreadyEncrypted(String::from("passport")))
+        );
+    }
+
+    #[test]
+    fn old_keys_still_decrypt() {
+        let mut form = get_form();
+        let old = Keyring::new("2023-01", &OLD_KEY).unwrap();
+        form.encrypt_fields(&["phone"], &old).unwrap();
+
+        let rotated = get_keys().with_key("2023-01", &OLD_KEY).unwrap();
+        form.decrypt_fields(&rotated).unwrap();
+        assert_eq!(form, get_form());
+
+        let mut form = get_form();
+        form.encrypt_fields(&["phone"], &old).unwrap();
+        assert_eq!(
+            form.decrypt_fields(&get_keys()),
+            Err(Error::UnknownKey(String::from("2023-01")))
+        );
+    }
+
+    #[test]
+    fn values_are_bound_to_their_field() {
+        let keys = get_keys();
+        let mut form = get_form();
+        form.encrypt_fields(&["phone"], &keys).unwrap();
+        let phone = form.remove("phone").unwrap();
+        form.insert("name", phone);
+        assert_eq!(form.decrypt_fields(&keys), Err(Error::Decrypt));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let keys = get_keys();
+        let mut form = get_form();
+        form.encrypt_fields(&["passport"], &keys).unwrap();
+        if let Some(Field::File(file)) = form.remove("passport") {
+            let mut bytes = file.bytes.to_vec();
+            *bytes.last_mut().unwrap() ^= 1;
+            form.insert(
+                "passport",
+                Field::File(FormFile {
+                    bytes: bytes.into(),
+                    ..file
+                }),
+            );
+        }
+        assert_eq!(form.decrypt_fields(&keys), Err(Error::Decrypt));
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert!(matches!(
+            Keyring::new("short", b"too short"),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            Keyring::new("has:colon", &KEY),
+            Err(Error::InvalidKeyId(_))
+        ));
+        assert!(matches!(
+            Keyring::new("", &KEY),
+            Err(Error::InvalidKeyId(_))
+        ));
+    }
+}
+
+/// The length in bytes of an encryption key.
+pub const KEY_LEN: usize = 32;
+/// The prefix of every encrypted text value.
+const TEXT_PREFIX: &str = "nebula-enc:v1:";
+/// The content type given to encrypted files. The key id is added as a
+/// `key-id` parameter.
+pub const ENCRYPTED_CONTENT_TYPE: &str = "application/vnd.nebula.encrypted";
+/// The filename given to encrypted files, whose real name is encrypted along
+/// with their contents.
+pub const ENCRYPTED_FILENAME: &str = "encrypted";
+const NONCE_LEN: usize = 24;
+
+/// Errors that can occur while encrypting or decrypting form fields.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A key was not `KEY_LEN` bytes long.
+    InvalidKey(String),
+    /// A key id was empty or contained characters that cannot be used in a
+    /// tag.
+    InvalidKeyId(String),
+    /// A value was encrypted with a key that is not in the `Keyring`.
+    UnknownKey(String),
+    /// A field that was expected to be encrypted is not.
+    NotEncrypted,
+    /// A field of a form that was about to be encrypted already looks
+    /// encrypted. Its name is included.
+    AlreadyEncrypted(String),
+    /// An encrypted value could not be parsed.
+    Malformed(String),
+    /// An encrypted value failed authentication, either because the wrong
+    /// key was used or because it was modified.
+    Decrypt,
+    /// The cipher could not encrypt a value.
+    Encrypt,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidKey(id) => write!(f, "Key {} must be {} bytes long", id, KEY_LEN),
+            Error::InvalidKeyId(id) => write!(f, "Invalid key id: {:?}", id),
+            Error::UnknownKey(id) => write!(f, "Unknown encryption key: {}", id),
+            Error::NotEncrypted => write!(f, "Field is not encrypted"),
+            Error::AlreadyEncrypted(name) => write!(f, "Field {} is already encrypted", name),
+            Error::Malformed(msg) => write!(f, "Malformed encrypted value: {}", msg),
+            Error::Decrypt => write!(f, "Encrypted value could not be authenticated"),
+            Error::Encrypt => write!(f, "Value could not be encrypted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The keys used to encrypt and decrypt fields, each identified by an id.
+///
+/// New values are always encrypted with the key given to `Keyring::new`.
+/// Keys added with `with_key` are only used to decrypt values that were
+/// tagged with their id, which allows keys to be rotated.
+///
+/// Requires `features = "encryption"`.
+#[derive(Clone)]
+pub struct Keyring {
+    current: String,
+    keys: HashMap<String, [u8; KEY_LEN]>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut ids: Vec<_> = self.keys.keys().collect();
+        ids.sort();
+        f.debug_struct("Keyring")
+            .field("current", &self.current)
+            .field("keys", &ids)
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// Creates a keyring that encrypts with `key`, tagging each value with
+    /// `key_id`. The id must be non-empty and must not contain whitespace,
+    /// `:`, `;`, `,` or quotes.
+    pub fn new(key_id: &str, key: &[u8]) -> Result<Self, Error> {
+        let keyring = Keyring {
+            current: key_id.to_string(),
+            keys: HashMap::new(),
+        };
+        keyring.with_key(key_id, key)
+    }
+
+    /// Adds a key that is used to decrypt values tagged with `key_id`.
+    pub fn with_key(mut self, key_id: &str, key: &[u8]) -> Result<Self, Error> {
+        let invalid = |c: char| c.is_whitespace() || [':', ';', ',', '"', '\''].contains(&c);
+        if key_id.is_empty() || key_id.contains(invalid) {
+            return Err(Error::InvalidKeyId(key_id.to_string()));
+        }
+        let key = key
+            .try_into()
+            .map_err(|_| Error::InvalidKey(key_id.to_string()))?;
+        self.keys.insert(key_id.to_string(), key);
+        Ok(self)
+    }
+
+    /// Returns the id of the key used for encryption.
+    pub fn key_id(&self) -> &str {
+        &self.current
+    }
+
+    fn cipher(&self, key_id: &str) -> Result<XChaCha20Poly1305, Error> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| Error::UnknownKey(key_id.to_string()))?;
+        Ok(XChaCha20Poly1305::new(key.into()))
+    }
+
+    /// Encrypts `msg` with the current key, binding it to the field `name`.
+    /// Returns the nonce followed by the ciphertext.
+    fn seal(&self, name: &str, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg,
+            aad: name.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher(&self.current)?
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| Error::Encrypt)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn open(&self, key_id: &str, name: &str, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        if sealed.len() < NONCE_LEN {
+            return Err(Error::Malformed(String::from("value is too short")));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: name.as_bytes(),
+        };
+        self.cipher(key_id)?
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| Error::Decrypt)
+    }
+}
+
+/// Appends `bytes` to `msg`, preceded by its length.
+fn push_bytes(msg: &mut Vec<u8>, bytes: &[u8]) {
+    msg.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    msg.extend_from_slice(bytes);
+}
+
+/// Removes a value written by `push_bytes` from the start of `msg`.
+fn take_bytes<'a>(msg: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let truncated = || Error::Malformed(String::from("file is truncated"));
+    let len = msg.get(..4).ok_or_else(truncated)?;
+    let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    let bytes = msg.get(4..4 + len).ok_or_else(truncated)?;
+    *msg = &msg[4 + len..];
+    Ok(bytes)
+}
+
+fn take_string(msg: &mut &[u8]) -> Result<String, Error> {
+    String::from_utf8(take_bytes(msg)?.to_vec()).map_err(|err| Error::Malformed(err.to_string()))
+}
+
+/// Returns the key id of an encrypted file's content type.
+fn file_key_id(content_type: &str) -> Option<&str> {
+    let mut parts = content_type.split(';').map(str::trim);
+    if parts.next()? != ENCRYPTED_CONTENT_TYPE {
+        return None;
+    }
+    parts.find_map(|param| param.strip_prefix("key-id="))
+}
+
+impl Field {
+    /// Returns the id of the key this field was encrypted with, or `None` if
+    /// it is not encrypted.
+    ///
+    /// Requires `features = "encryption"`.
+    pub fn encryption_key_id(&self) -> Option<&str> {
+        match self {
+            Field::Text(text) => {
+                let rest = text.strip_prefix(TEXT_PREFIX)?;
+                rest.split(':').next()
+            }
+            Field::File(file) => file_key_id(&file.content_type),
+        }
+    }
+
+    /// Returns a copy of this field encrypted with the keyring's current key.
+    ///
+    /// Text is replaced by a tagged string. A file's content type, filename,
+    /// part headers, and contents are encrypted together; the filename is
+    /// replaced by `ENCRYPTED_FILENAME` and the headers are removed. The
+    /// result is bound to the field `name` and cannot be decrypted under
+    /// another name.
+    ///
+    /// Requires `features = "encryption"`.
+    pub fn encrypt(&self, name: &str, keys: &Keyring) -> Result<Field, Error> {
+        match self {
+            Field::Text(text) => {
+                let sealed = keys.seal(name, text.as_bytes())?;
+                Ok(Field::Text(format!(
+                    "{}{}:{}",
+                    TEXT_PREFIX,
+                    keys.key_id(),
+                    base64::encode_config(&sealed, base64::URL_SAFE_NO_PAD)
+                )))
+            }
+            Field::File(file) => {
+                let mut msg = Vec::with_capacity(file.bytes.len() + 64);
+                push_bytes(&mut msg, file.content_type.as_bytes());
+                push_bytes(&mut msg, file.filename.as_bytes());
+                msg.extend_from_slice(&(file.headers.len() as u32).to_be_bytes());
+                for (header, value) in file.headers.iter() {
+                    push_bytes(&mut msg, header.as_str().as_bytes());
+                    push_bytes(&mut msg, value.as_bytes());
+                }
+                msg.extend_from_slice(&file.bytes);
+
+                Ok(Field::File(FormFile {
+                    filename: String::from(ENCRYPTED_FILENAME),
+                    content_type: format!("{}; key-id={}", ENCRYPTED_CONTENT_TYPE, keys.key_id()),
+                    bytes: keys.seal(name, &msg)?.into(),
+                    headers: HeaderMap::new(),
+                }))
+            }
+        }
+    }
+
+    /// Returns a decrypted copy of a field that was encrypted under `name`.
+    ///
+    /// Requires `features = "encryption"`.
+    pub fn decrypt(&self, name: &str, keys: &Keyring) -> Result<Field, Error> {
+        let key_id = self.encryption_key_id().ok_or(Error::NotEncrypted)?;
+        match self {
+            Field::Text(text) => {
+                let encoded = &text[TEXT_PREFIX.len() + key_id.len()..];
+                let encoded = encoded
+                    .strip_prefix(':')
+                    .ok_or_else(|| Error::Malformed(String::from("missing key id")))?;
+                let sealed = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+                    .map_err(|err| Error::Malformed(err.to_string()))?;
+                let text = String::from_utf8(keys.open(key_id, name, &sealed)?)
+                    .map_err(|err| Error::Malformed(err.to_string()))?;
+                Ok(Field::Text(text))
+            }
+            Field::File(file) => {
+                let opened = keys.open(key_id, name, &file.bytes)?;
+                let mut msg = &opened[..];
+                let content_type = take_string(&mut msg)?;
+                let filename = take_string(&mut msg)?;
+
+                let count = msg
+                    .get(..4)
+                    .ok_or_else(|| Error::Malformed(String::from("file is truncated")))?;
+                let count = u32::from_be_bytes(count.try_into().unwrap());
+                msg = &msg[4..];
+                let mut headers = HeaderMap::new();
+                for _ in 0..count {
+                    let header = HeaderName::from_bytes(take_bytes(&mut msg)?)
+                        .map_err(|err| Error::Malformed(err.to_string()))?;
+                    let value = HeaderValue::from_bytes(take_bytes(&mut msg)?)
+                        .map_err(|err| Error::Malformed(err.to_string()))?;
+                    headers.append(header, value);
+                }
+
+                Ok(Field::File(FormFile {
+                    filename,
+                    content_type,
+                    bytes: msg.to_vec().into(),
+                    headers,
+                }))
+            }
+        }
+    }
+}
+
+impl Form {
+    /// Encrypts the named fields with the keyring's current key. Names that
+    /// are not in the form are left alone. If any field fails to encrypt, the
+    /// form is not modified.
+    ///
+    /// Whether a field is encrypted is only known from its value, which a
+    /// client could imitate, so the form is expected to come straight from
+    /// the client. If any field already looks encrypted,
+    /// `Error::AlreadyEncrypted` is returned instead.
+    ///
+    /// Encrypted fields remain ordinary `Text` and `File` fields, so the form
+    /// can still be stored or sent anywhere a `Form` can.
+    ///
+    /// Requires `features = "encryption"`.
+    pub fn encrypt_fields(&mut self, names: &[&str], keys: &Keyring) -> Result<(), Error> {
+        if let Some((name, _)) = self
+            .iter()
+            .find(|(_, field)| field.encryption_key_id().is_some())
+        {
+            return Err(Error::AlreadyEncrypted(name.clone()));
+        }
+
+        let mut encrypted = Vec::with_capacity(names.len());
+        for name in names {
+            if let Some(field) = self.get(name) {
+                encrypted.push((*name, field.encrypt(name, keys)?));
+            }
+        }
+        for (name, field) in encrypted {
+            self.insert(name, field);
+        }
+        Ok(())
+    }
+
+    /// Decrypts every encrypted field in the form. If any field fails to
+    /// decrypt, the form is not modified.
+    ///
+    /// Requires `features = "encryption"`.
+    pub fn decrypt_fields(&mut self, keys: &Keyring) -> Result<(), Error> {
+        let mut decrypted = Vec::new();
+        for (name, field) in self.iter() {
+            if field.encryption_key_id().is_some() {
+                decrypted.push((name.clone(), field.decrypt(name, keys)?));
+            }
+        }
+        for (name, field) in decrypted {
+            self.insert(&name, field);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{Field, Form, FormFile};
+use serde_json::{Map, Value};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeaderMap;
+    use bytes::Bytes;
+
+    fn get_form() -> Form {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("Jane Doe")));
+        form.insert(
+            "message",
+            Field::Text(String::from("Hello, \"world\"\nSecond line")),
+        );
+        form.insert(
+            "resume",
+            Field::File(FormFile {
+                filename: String::from("resume.txt"),
+                content_type: String::from("text/plain"),
+                bytes: Bytes::from_static(b"hello"),
+                headers: HeaderMap::new(),
+            }),
+        );
+        form
+    }
+
+    fn get_fields() -> Vec<String> {
+        vec![
+            String::from("name"),
+            String::from("resume"),
+            String::from("missing"),
+            String::from("message"),
+        ]
+    }
+
+    #[test]
+    fn json_file_metadata() {
+        let json = get_form().to_json(&ExportOptions::new());
+        let expected = serde_json::json!({
+            "message": "Hello, \"world\"\nSecond line",
+            "name": "Jane Doe",
+            "resume": {
+                "filename": "resume.txt",
+                "content_type": "text/plain",
+                "size": 5,
+            },
+        });
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn json_file_base64() {
+        let opts = ExportOptions::new().files(FileExport::Base64);
+        let json = get_form().to_json(&opts);
+        assert_eq!(json["resume"]["content"], "aGVsbG8=");
+        assert_eq!(json["resume"].get("size"), None);
+    }
+
+    #[test]
+    fn json_explicit_fields_keep_order_and_missing_as_null() {
+        let opts = ExportOptions::new().fields(get_fields());
+        let json = get_form().to_json(&opts);
+        let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(keys, get_fields().iter().collect::<Vec<_>>());
+        assert_eq!(json["missing"], Value::Null);
+    }
+
+    #[test]
+    fn csv_row_follows_field_order() {
+        let opts = ExportOptions::new().fields(get_fields());
+        let form = get_form();
+        assert_eq!(opts.csv_header(&form), "name,resume,missing,message");
+        assert_eq!(
+            form.to_csv_row(&opts),
+            "Jane Doe,resume.txt (text/plain; 5 bytes),,\"Hello, \"\"world\"\"\nSecond line\""
+        );
+    }
+
+    #[test]
+    fn csv_row_default_order_is_sorted() {
+        let opts = ExportOptions::new().files(FileExport::Base64);
+        let form = get_form();
+        assert_eq!(opts.csv_header(&form), "message,name,resume");
+        assert_eq!(
+            form.to_csv_row(&opts),
+            "\"Hello, \"\"world\"\"\nSecond line\",Jane Doe,aGVsbG8="
+        );
+    }
+
+    #[test]
+    fn text_body_indents_continuation_lines() {
+        let opts = ExportOptions::new().fields(get_fields());
+        assert_eq!(
+            get_form().to_text(&opts),
+            "name: Jane Doe\n\
+             resume: resume.txt (text/plain; 5 bytes)\n\
+             message: Hello, \"world\"\n    Second line\n"
+        );
+    }
+}
+
+/// Determines how file fields are represented when exporting a `Form`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileExport {
+    /// Only the filename, content type, and size of the file are included.
+    #[default]
+    Metadata,
+    /// The file's content is included, encoded as standard base64.
+    Base64,
+}
+
+/// Options controlling how a `Form` is rendered by `Form::to_json`,
+/// `Form::to_csv_row`, and `Form::to_text`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    files: FileExport,
+    fields: Option<Vec<String>>,
+}
+
+impl ExportOptions {
+    /// Creates a new set of options that exports file metadata and every
+    /// field of the form, sorted by name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how file fields are represented.
+    pub fn files(mut self, files: FileExport) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Sets an explicit list of fields to export, in order. Fields that are
+    /// not in the list are skipped, and fields in the list that are missing
+    /// from the form are exported as empty values.
+    pub fn fields(mut self, fields: Vec<String>) -> Self {
+        self.fields = Some(fields);
+        self
+    }
+
+    /// Returns the names of the fields to export from `form`, in order.
+    fn field_names<'a>(&'a self, form: &'a Form) -> Vec<&'a str> {
+        match &self.fields {
+            Some(fields) => fields.iter().map(String::as_str).collect(),
+            None => {
+                let mut names: Vec<&str> = form.iter().map(|(name, _)| name.as_str()).collect();
+                names.sort_unstable();
+                names
+            }
+        }
+    }
+
+    /// Returns the CSV header row matching the rows produced by
+    /// `Form::to_csv_row` with these options. The row is not terminated by
+    /// a newline.
+    pub fn csv_header(&self, form: &Form) -> String {
+        self.field_names(form)
+            .into_iter()
+            .map(csv_escape)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Renders a file as a single line of text according to these options.
+    fn file_to_string(&self, file: &FormFile) -> String {
+        match self.files {
+            FileExport::Metadata => format!(
+                "{} ({}; {} bytes)",
+                file.filename,
+                file.content_type,
+                file.bytes.len()
+            ),
+            FileExport::Base64 => base64::encode(&file.bytes),
+        }
+    }
+
+    /// Renders a file as a JSON object according to these options.
+    fn file_to_json(&self, file: &FormFile) -> Value {
+        let mut obj = Map::new();
+        obj.insert(
+            String::from("filename"),
+            Value::from(file.filename.as_str()),
+        );
+        obj.insert(
+            String::from("content_type"),
+            Value::from(file.content_type.as_str()),
+        );
+        match self.files {
+            FileExport::Metadata => {
+                obj.insert(String::from("size"), Value::from(file.bytes.len()));
+            }
+            FileExport::Base64 => {
+                obj.insert(
+                    String::from("content"),
+                    Value::from(base64::encode(&file.bytes)),
+                );
+            }
+        }
+        Value::Object(obj)
+    }
+}
+
+/// Quotes a CSV cell if it contains a delimiter, quote, or line break, as
+/// described in RFC 4180.
+fn csv_escape(cell: &str) -> String {
+    if cell.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+impl Form {
+    /// Returns the `Form` as a JSON object mapping field names to values, in
+    /// the order given by `opts`.
+    ///
+    /// Text fields become JSON strings and file fields become objects with
+    /// `filename` and `content_type` members, plus either `size` or
+    /// base64-encoded `content` depending on `opts`.
+    pub fn to_json(&self, opts: &ExportOptions) -> Value {
+        let mut obj = Map::new();
+        for name in opts.field_names(self) {
+            let value = match self.get(name) {
+                None => Value::Null,
+                Some(Field::Text(txt)) => Value::from(txt.as_str()),
+                Some(Field::File(file)) => opts.file_to_json(file),
+            };
+            obj.insert(name.to_string(), value);
+        }
+        Value::Object(obj)
+    }
+
+    /// Returns the `Form` as a single CSV record, with columns in the order
+    /// given by `ExportOptions::csv_header`. The row is not terminated by a
+    /// newline.
+    pub fn to_csv_row(&self, opts: &ExportOptions) -> String {
+        opts.field_names(self)
+            .into_iter()
+            .map(|name| match self.get(name) {
+                None => String::new(),
+                Some(Field::Text(txt)) => csv_escape(txt),
+                Some(Field::File(file)) => csv_escape(&opts.file_to_string(file)),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns the `Form` as a plain-text body with one `name: value` line
+    /// per field, suitable for an email. Continuation lines of multi-line
+    /// values are indented. Fields missing from the form are skipped.
+    pub fn to_text(&self, opts: &ExportOptions) -> String {
+        let mut text = String::new();
+        for name in opts.field_names(self) {
+            let value = match self.get(name) {
+                None => continue,
+                Some(Field::Text(txt)) => txt.replace("\r\n", "\n"),
+                Some(Field::File(file)) => opts.file_to_string(file),
+            };
+
+            text.push_str(name);
+            text.push_str(": ");
+            text.push_str(&value.replace('\n', "\n    "));
+            text.push('\n');
+        }
+        text
+    }
+}
@@ -0,0 +1,765 @@
+use crate::{Field, Form, FormFile, HeaderMap};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbImage};
+
+    const SECRET: &[u8] = b"GPS 51.5007N 0.1246W";
+
+    fn get_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 40) as u8, (y * 40) as u8, 128])
+        }))
+    }
+
+    /// Builds an EXIF APP1 payload with an orientation tag followed by some
+    /// data that stands in for other, private, tags.
+    fn get_exif(orientation: u16) -> Vec<u8> {
+        let mut exif = minimal_exif(orientation);
+        // Point the "next IFD" offset past the end to make sure it is ignored
+        let len = exif.len();
+        exif[len - 4..].copy_from_slice(&[0, 0, 0xff, 0xff]);
+        exif.extend_from_slice(SECRET);
+        exif
+    }
+
+    fn get_jpeg(orientation: u16) -> FormFile {
+        let mut plain = Vec::new();
+        get_image(4, 2)
+            .write_to(&mut Cursor::new(&mut plain), ImageFormat::Jpeg)
+            .unwrap();
+
+        // Insert EXIF and a comment directly after SOI
+        let exif = get_exif(orientation);
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        bytes.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        bytes.extend_from_slice(&exif);
+        bytes.extend_from_slice(&[0xFF, 0xFE, 0x00, (SECRET.len() + 2) as u8]);
+        bytes.extend_from_slice(SECRET);
+        bytes.extend_from_slice(&plain[2..]);
+
+        FormFile {
+            filename: String::from("photo.JPG"),
+            content_type: String::from("image/jpeg"),
+            bytes: bytes.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn get_png() -> FormFile {
+        let mut plain = Vec::new();
+        get_image(4, 2)
+            .write_to(&mut Cursor::new(&mut plain), ImageFormat::Png)
+            .unwrap();
+
+        // Insert a tEXt chunk directly after IHDR. Its CRC is invalid, but the
+        // chunk is removed before the image is decoded.
+        let ihdr_end = 8 + 12 + 13;
+        let mut bytes = plain[..ihdr_end].to_vec();
+        bytes.extend_from_slice(&(SECRET.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(b"tEXt");
+        bytes.extend_from_slice(SECRET);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(&plain[ihdr_end..]);
+
+        FormFile {
+            filename: String::from("drawing.png"),
+            content_type: String::from("image/png"),
+            bytes: bytes.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn get_webp() -> FormFile {
+        let mut plain = Vec::new();
+        get_image(4, 2)
+            .write_to(&mut Cursor::new(&mut plain), ImageFormat::WebP)
+            .unwrap();
+
+        // Rebuild the file as an extended WebP with an EXIF chunk
+        let mut chunks = Vec::new();
+        chunks.extend_from_slice(b"VP8X");
+        chunks.extend_from_slice(&10u32.to_le_bytes());
+        chunks.extend_from_slice(&[0x08, 0, 0, 0, 3, 0, 0, 1, 0, 0]);
+        chunks.extend_from_slice(&plain[12..]);
+        chunks.extend_from_slice(b"EXIF");
+        chunks.extend_from_slice(&(SECRET.len() as u32).to_le_bytes());
+        chunks.extend_from_slice(SECRET);
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WEBP");
+        bytes.extend_from_slice(&chunks);
+
+        FormFile {
+            filename: String::from("sticker"),
+            content_type: String::from("application/octet-stream"),
+            bytes: bytes.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn contains_secret(file: &FormFile) -> bool {
+        file.bytes.windows(SECRET.len()).any(|win| win == SECRET)
+    }
+
+    fn decode(file: &FormFile) -> DynamicImage {
+        image::load_from_memory(&file.bytes).expect("output should be a valid image")
+    }
+
+    #[test]
+    fn fixtures_contain_secret() {
+        assert!(contains_secret(&get_jpeg(1)));
+        assert!(contains_secret(&get_png()));
+        assert!(contains_secret(&get_webp()));
+        assert_eq!(decode(&get_webp()).dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn strip_jpeg_metadata() {
+        let file = get_jpeg(1).strip_metadata().unwrap();
+        assert!(!contains_secret(&file));
+        assert!(!file.bytes.windows(4).any(|win| win == b"Exif"));
+        assert_eq!(decode(&file).dimensions(), (4, 2));
+        assert_eq!(file.content_type, "image/jpeg");
+        assert_eq!(file.filename, "photo.JPG");
+    }
+
+    #[test]
+    fn strip_jpeg_drops_trailing_data() {
+        // Multi-picture JPEGs store more images, with their own EXIF, after
+        // the end of the first one
+        let mut file = get_jpeg(1);
+        file.bytes = [&file.bytes[..], &get_jpeg(1).bytes[..]].concat().into();
+
+        let stripped = file.clone().strip_metadata().unwrap();
+        assert!(!contains_secret(&stripped));
+        assert!(stripped.bytes.ends_with(&[0xFF, 0xD9]));
+        assert!(stripped.bytes.len() < file.bytes.len() / 2);
+        assert_eq!(decode(&stripped).dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn strip_jpeg_keeps_orientation() {
+        let file = get_jpeg(6).strip_metadata().unwrap();
+        assert!(!contains_secret(&file));
+        assert_eq!(parse_jpeg(&file.bytes).unwrap().orientation, Some(6));
+    }
+
+    #[test]
+    fn strip_png_metadata() {
+        let file = get_png().strip_metadata().unwrap();
+        assert!(!contains_secret(&file));
+        assert_eq!(decode(&file).dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn strip_webp_metadata() {
+        let file = get_webp().strip_metadata().unwrap();
+        assert!(!contains_secret(&file));
+        assert_eq!(file.bytes[12..16], b"VP8X"[..]);
+        assert_eq!(file.bytes[20] & 0x0C, 0);
+        assert_eq!(decode(&file).dimensions(), (4, 2));
+        assert_eq!(file.content_type, "image/webp");
+        assert_eq!(file.filename, "sticker.webp");
+    }
+
+    #[test]
+    fn strip_rejects_unsupported() {
+        let file = FormFile {
+            filename: String::from("notes.txt"),
+            content_type: String::from("image/jpeg"),
+            bytes: b"not an image"[..].into(),
+            headers: HeaderMap::new(),
+        };
+        match file.strip_metadata() {
+            Err(Error::Unsupported(content_type)) => assert_eq!(content_type, "image/jpeg"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn process_downscales_and_orients() {
+        let opts = ImageOptions::new().max_dimension(2);
+        let processed = get_jpeg(6).process_image(&opts).unwrap();
+        assert!(!contains_secret(&processed.file));
+        assert_eq!(decode(&processed.file).dimensions(), (1, 2));
+        assert!(processed.thumbnail.is_none());
+    }
+
+    #[test]
+    fn process_small_image_is_only_stripped() {
+        let opts = ImageOptions::new().max_dimension(100);
+        let processed = get_png().process_image(&opts).unwrap();
+        assert_eq!(processed.file, get_png().strip_metadata().unwrap());
+    }
+
+    #[test]
+    fn process_thumbnail() {
+        let opts = ImageOptions::new().thumbnail(2);
+        let processed = get_png().process_image(&opts).unwrap();
+        let thumbnail = processed.thumbnail.unwrap();
+        assert_eq!(decode(&processed.file).dimensions(), (4, 2));
+        assert_eq!(decode(&thumbnail).dimensions(), (2, 1));
+        assert_eq!(thumbnail.filename, "drawing-thumbnail.png");
+        assert_eq!(thumbnail.content_type, "image/png");
+    }
+
+    #[test]
+    fn form_process_images_skips_other_fields() {
+        let text = FormFile {
+            filename: String::from("notes.txt"),
+            content_type: String::from("text/plain"),
+            bytes: SECRET.into(),
+            headers: HeaderMap::new(),
+        };
+        let mut form = Form::new();
+        form.insert("photo", Field::File(get_jpeg(1)));
+        form.insert("notes", Field::File(text.clone()));
+        form.insert("name", Field::Text(String::from("Jane")));
+
+        let thumbnails = form
+            .process_images(&ImageOptions::new().thumbnail(1))
+            .unwrap();
+
+        assert_eq!(thumbnails.len(), 1);
+        assert_eq!(thumbnails[0].0, "photo");
+        assert!(!contains_secret(
+            form.get("photo").unwrap().as_file().unwrap()
+        ));
+        assert_eq!(form.get("notes"), Some(&Field::File(text)));
+    }
+}
+
+/// Errors that can occur while processing uploaded images.
+#[derive(Debug)]
+pub enum Error {
+    /// The file is not a JPEG, PNG, or WebP image. Contains the file's
+    /// declared content type.
+    Unsupported(String),
+    /// The file looked like a supported image but could not be parsed.
+    Malformed(String),
+    /// The processed image could not be encoded.
+    Encode(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Unsupported(content_type) => {
+                write!(f, "Unsupported image type: {}", content_type)
+            }
+            Error::Malformed(msg) => write!(f, "Malformed image: {}", msg),
+            Error::Encode(msg) => write!(f, "Could not encode image: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The image formats that can be processed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl Format {
+    /// Detects the format from the file's content, ignoring its declared
+    /// content type.
+    fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Format::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Format::Png)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Format::WebP)
+        } else {
+            None
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Jpeg => "image/jpeg",
+            Format::Png => "image/png",
+            Format::WebP => "image/webp",
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Jpeg => &["jpg", "jpeg", "jpe", "jfif"],
+            Format::Png => &["png"],
+            Format::WebP => &["webp"],
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Jpeg => ImageFormat::Jpeg,
+            Format::Png => ImageFormat::Png,
+            Format::WebP => ImageFormat::WebP,
+        }
+    }
+
+    /// Returns `filename` with an extension that matches this format,
+    /// replacing the existing one if it does not.
+    fn filename(self, filename: &str) -> String {
+        let (stem, ext) = split_extension(filename);
+        match ext {
+            Some(ext) if self.extensions().contains(&ext.to_lowercase().as_str()) => {
+                filename.to_string()
+            }
+            _ => format!("{}.{}", stem, self.extensions()[0]),
+        }
+    }
+}
+
+/// Splits a filename into its stem and extension, if it has one.
+fn split_extension(filename: &str) -> (&str, Option<&str>) {
+    match filename.rfind('.') {
+        Some(idx) if idx > 0 => (&filename[..idx], Some(&filename[idx + 1..])),
+        _ => (filename, None),
+    }
+}
+
+/// Options for `FormFile::process_image` and `Form::process_images`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageOptions {
+    max_dimension: Option<u32>,
+    thumbnail: Option<u32>,
+    jpeg_quality: u8,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            max_dimension: None,
+            thumbnail: None,
+            jpeg_quality: 85,
+        }
+    }
+}
+
+impl ImageOptions {
+    /// Creates options that only strip metadata.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Downscales images whose width or height exceeds `max`, preserving
+    /// the aspect ratio.
+    pub fn max_dimension(mut self, max: u32) -> Self {
+        self.max_dimension = Some(max);
+        self
+    }
+
+    /// Generates a thumbnail that fits within a `size` by `size` square.
+    pub fn thumbnail(mut self, size: u32) -> Self {
+        self.thumbnail = Some(size);
+        self
+    }
+
+    /// Sets the quality (1-100) used when a JPEG has to be re-encoded.
+    /// Defaults to 85.
+    pub fn jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+}
+
+/// The result of `FormFile::process_image`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Processed {
+    /// The image with its metadata removed and, if necessary, downscaled.
+    pub file: FormFile,
+    /// The thumbnail, if one was requested.
+    pub thumbnail: Option<FormFile>,
+}
+
+fn malformed(what: &str) -> Error {
+    Error::Malformed(what.to_string())
+}
+
+/// Reads the orientation tag from a TIFF structure, as found in an EXIF
+/// APP1 segment after the `Exif\0\0` header.
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |off: usize| {
+        let b = tiff.get(off..off + 2)?;
+        Some(if big_endian {
+            u16::from_be_bytes([b[0], b[1]])
+        } else {
+            u16::from_le_bytes([b[0], b[1]])
+        })
+    };
+    let u32_at = |off: usize| {
+        let b = tiff.get(off..off + 4)?;
+        Some(if big_endian {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Builds an EXIF APP1 payload that contains nothing but the orientation.
+fn minimal_exif(orientation: u16) -> Vec<u8> {
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+    exif.extend_from_slice(&orientation.to_be_bytes());
+    exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    exif
+}
+
+/// The segments of a JPEG file before the image data.
+struct JpegParts<'a> {
+    /// Segments to keep, including their markers.
+    segments: Vec<&'a [u8]>,
+    /// Everything from the start-of-scan marker to the end-of-image marker.
+    scan: &'a [u8],
+    orientation: Option<u16>,
+}
+
+fn parse_jpeg(data: &[u8]) -> Result<JpegParts<'_>, Error> {
+    let mut parts = JpegParts {
+        segments: Vec::new(),
+        scan: &[],
+        orientation: None,
+    };
+    let mut pos = 2;
+
+    loop {
+        if data.get(pos) != Some(&0xFF) {
+            return Err(malformed("expected JPEG marker"));
+        }
+        let start = pos;
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while data.get(pos) == Some(&0xFF) {
+            pos += 1;
+        }
+        let marker = *data.get(pos).ok_or_else(|| malformed("truncated JPEG"))?;
+        pos += 1;
+
+        match marker {
+            0xDA | 0xD9 => {
+                parts.scan = &data[start..end_of_image(data, start)];
+                return Ok(parts);
+            }
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+
+        let len = data
+            .get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .filter(|&len| len >= 2 && pos + len <= data.len())
+            .ok_or_else(|| malformed("invalid JPEG segment length"))?;
+        let payload = &data[pos + 2..pos + len];
+        pos += len;
+
+        let keep = match marker {
+            // JFIF header
+            0xE0 => true,
+            0xE1 => {
+                if payload.starts_with(b"Exif\0\0") {
+                    parts.orientation = exif_orientation(&payload[6..]);
+                }
+                false
+            }
+            // Color profile, needed to display colors correctly
+            0xE2 => payload.starts_with(b"ICC_PROFILE\0"),
+            // Adobe color transform, needed to decode CMYK images
+            0xEE => payload.starts_with(b"Adobe"),
+            // All other application segments and comments
+            0xE3..=0xEF | 0xFE => false,
+            _ => true,
+        };
+
+        if keep {
+            parts.segments.push(&data[start..pos]);
+        }
+    }
+}
+
+/// Returns the position just after the end-of-image marker that follows the
+/// scan at `start`, or the end of `data` if there is none. Anything after it,
+/// such as the extra images of a multi-picture JPEG, is not part of the image.
+fn end_of_image(data: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while pos < data.len() {
+        if data[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let mut next = pos + 1;
+        while data.get(next) == Some(&0xFF) {
+            next += 1;
+        }
+        match data.get(next) {
+            None => break,
+            Some(0xD9) => return next + 1,
+            // Stuffed bytes and restart markers within the scan
+            Some(0x00) | Some(0x01) | Some(0xD0..=0xD7) => pos = next + 1,
+            // Segments between the scans of a progressive JPEG, whose
+            // payload is skipped as it may contain any bytes
+            Some(_) => match data.get(next + 1..next + 3) {
+                Some(len) => pos = next + 1 + u16::from_be_bytes([len[0], len[1]]) as usize,
+                None => break,
+            },
+        }
+    }
+    data.len()
+}
+
+fn strip_jpeg(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let parts = parse_jpeg(data)?;
+    let mut out = vec![0xFF, 0xD8];
+    let mut segments = parts.segments.into_iter().peekable();
+
+    // JFIF requires its header to come first
+    if let Some(jfif) = segments.next_if(|seg| seg.get(1) == Some(&0xE0)) {
+        out.extend_from_slice(jfif);
+    }
+
+    // Keep the orientation so the image is still displayed upright
+    if let Some(orientation) = parts.orientation.filter(|&o| o != 1) {
+        let exif = minimal_exif(orientation);
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&exif);
+    }
+
+    for seg in segments {
+        out.extend_from_slice(seg);
+    }
+    out.extend_from_slice(parts.scan);
+    Ok(out)
+}
+
+fn strip_png(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = data[..8].to_vec();
+    let mut pos = 8;
+
+    loop {
+        let len = data
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| malformed("truncated PNG"))?;
+        let end = pos
+            .checked_add(len)
+            .and_then(|end| end.checked_add(12))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| malformed("invalid PNG chunk length"))?;
+        let kind = &data[pos + 4..pos + 8];
+
+        match kind {
+            b"tEXt" | b"zTXt" | b"iTXt" | b"eXIf" | b"tIME" => {}
+            _ => out.extend_from_slice(&data[pos..end]),
+        }
+
+        pos = end;
+        if kind == b"IEND" {
+            return Ok(out);
+        }
+    }
+}
+
+fn strip_webp(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let riff_len = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let end = riff_len
+        .checked_add(8)
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| malformed("invalid RIFF length"))?;
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+
+    while pos < end {
+        let len = data
+            .get(pos + 4..pos + 8)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| malformed("truncated WebP"))?;
+        // Chunks are padded to an even length
+        let chunk_end = (len + (len & 1))
+            .checked_add(pos + 8)
+            .filter(|&chunk_end| chunk_end <= end)
+            .ok_or_else(|| malformed("invalid WebP chunk length"))?;
+        let kind = &data[pos..pos + 4];
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if len >= 1 => {
+                let mut chunk = data[pos..chunk_end].to_vec();
+                // Clear the EXIF and XMP flags
+                chunk[8] &= !0x0C;
+                chunks.extend_from_slice(&chunk);
+            }
+            _ => chunks.extend_from_slice(&data[pos..chunk_end]),
+        }
+        pos = chunk_end;
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(&chunks);
+    Ok(out)
+}
+
+/// Applies an EXIF orientation to the decoded image.
+fn orient(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+fn encode(img: &DynamicImage, format: Format, jpeg_quality: u8) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    let result = match format {
+        // Neither encoder supports every pixel format the decoders produce
+        Format::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, jpeg_quality)),
+        Format::Png => img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png),
+        Format::WebP => DynamicImage::ImageRgba8(img.to_rgba8())
+            .write_to(&mut Cursor::new(&mut buf), ImageFormat::WebP),
+    };
+    result.map_err(|e| Error::Encode(e.to_string()))?;
+    Ok(buf)
+}
+
+impl FormFile {
+    /// Returns a copy of this JPEG, PNG, or WebP image with its metadata,
+    /// such as EXIF location data, comments, and XMP, removed. The image
+    /// data itself is not re-encoded.
+    ///
+    /// The image type is detected from the file's content. The returned
+    /// file's `content_type` matches that type, and its `filename` gets a
+    /// matching extension if it does not already have one.
+    ///
+    /// Requires `features = "images"`.
+    pub fn strip_metadata(&self) -> Result<FormFile, Error> {
+        let format = Format::detect(&self.bytes)
+            .ok_or_else(|| Error::Unsupported(self.content_type.clone()))?;
+        let bytes = match format {
+            Format::Jpeg => strip_jpeg(&self.bytes)?,
+            Format::Png => strip_png(&self.bytes)?,
+            Format::WebP => strip_webp(&self.bytes)?,
+        };
+
+        Ok(FormFile {
+            filename: format.filename(&self.filename),
+            content_type: format.content_type().to_string(),
+            bytes: bytes.into(),
+            headers: self.headers.clone(),
+        })
+    }
+
+    /// Strips the image's metadata and, depending on `opts`, downscales it
+    /// and generates a thumbnail in the same format.
+    ///
+    /// Images that are re-encoded are rotated according to their EXIF
+    /// orientation first.
+    ///
+    /// Requires `features = "images"`.
+    pub fn process_image(&self, opts: &ImageOptions) -> Result<Processed, Error> {
+        let mut file = self.strip_metadata()?;
+        if opts.max_dimension.is_none() && opts.thumbnail.is_none() {
+            return Ok(Processed {
+                file,
+                thumbnail: None,
+            });
+        }
+
+        // Only the stripped image is decoded, so the decoder never sees the
+        // original metadata.
+        let stripped = file.bytes.clone();
+        let format = Format::detect(&stripped).ok_or_else(|| malformed("unknown format"))?;
+        let orientation = match format {
+            Format::Jpeg => parse_jpeg(&stripped)?.orientation.unwrap_or(1),
+            Format::Png | Format::WebP => 1,
+        };
+        let img = image::load_from_memory_with_format(&stripped, format.image_format())
+            .map_err(|e| Error::Malformed(e.to_string()))?;
+        let img = orient(img, orientation);
+
+        if let Some(max) = opts.max_dimension {
+            if img.width() > max || img.height() > max {
+                let resized = img.resize(max, max, FilterType::Lanczos3);
+                file.bytes = encode(&resized, format, opts.jpeg_quality)?.into();
+            }
+        }
+
+        let thumbnail = opts
+            .thumbnail
+            .map(|size| {
+                let (stem, _) = split_extension(&file.filename);
+                Ok(FormFile {
+                    filename: format!("{}-thumbnail.{}", stem, format.extensions()[0]),
+                    content_type: file.content_type.clone(),
+                    bytes: encode(&img.thumbnail(size, size), format, opts.jpeg_quality)?.into(),
+                    headers: HeaderMap::new(),
+                })
+            })
+            .transpose()?;
+
+        Ok(Processed { file, thumbnail })
+    }
+}
+
+impl Form {
+    /// Runs `FormFile::process_image` on every file field that contains a
+    /// JPEG, PNG, or WebP image, replacing the field with the processed
+    /// image. Other fields are left untouched.
+    ///
+    /// Returns the generated thumbnails, paired with the name of the field
+    /// they were generated from.
+    ///
+    /// Requires `features = "images"`.
+    pub fn process_images(
+        &mut self,
+        opts: &ImageOptions,
+    ) -> Result<Vec<(String, FormFile)>, Error> {
+        let mut thumbnails = Vec::new();
+
+        for (name, field) in self.0.iter_mut() {
+            let file = match field {
+                Field::File(file) if Format::detect(&file.bytes).is_some() => file,
+                _ => continue,
+            };
+
+            let processed = file.process_image(opts)?;
+            *file = processed.file;
+            if let Some(thumbnail) = processed.thumbnail {
+                thumbnails.push((name.clone(), thumbnail));
+            }
+        }
+
+        Ok(thumbnails)
+    }
+}
@@ -3,24 +3,64 @@ use bytes::Buf;
 use bytes::Bytes;
 #[cfg(feature = "server-warp")]
 use futures::stream::Stream;
+#[cfg(all(test, feature = "server-warp"))]
+use futures::StreamExt;
 #[cfg(feature = "server-warp")]
-use futures::{StreamExt, TryStreamExt};
+use futures::TryStreamExt;
+pub use http::HeaderMap;
 #[cfg(feature = "server-warp")]
 use nebula_status::{Status, StatusCode};
 use std::collections::HashMap;
-#[cfg(feature = "server-warp")]
+#[cfg(all(test, feature = "server-warp"))]
+use std::collections::HashSet;
 use std::error::Error as StdError;
-#[cfg(feature = "server-warp")]
 use std::fmt::{self, Display, Formatter};
 use std::str;
 use std::str::FromStr;
 #[cfg(feature = "server-warp")]
-use warp::filters::multipart::{FormData, Part};
+use std::sync::Arc;
+#[cfg(all(test, feature = "server-warp"))]
+use warp::filters::multipart::FormData;
+#[cfg(feature = "server-warp")]
+use warp::filters::multipart::Part;
 #[cfg(feature = "server-warp")]
 use warp::reject::{Reject, Rejection};
 #[cfg(feature = "server-warp")]
 use warp::Filter;
 
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "archives")]
+pub mod archive;
+pub mod definition;
+#[cfg(feature = "definition-files")]
+pub mod definition_file;
+pub mod diff;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod export;
+#[cfg(feature = "images")]
+pub mod images;
+mod multipart;
+pub mod normalize;
+pub mod policy;
+#[cfg(feature = "server-warp")]
+pub mod scan;
+#[cfg(feature = "sessions")]
+pub mod session;
+
+pub use definition::{FieldDefinition, FieldType, FormDefinition};
+#[cfg(feature = "definition-files")]
+pub use definition_file::LoadError;
+pub use diff::{FormDiff, MergeStrategy};
+#[cfg(feature = "encryption")]
+pub use encryption::Keyring;
+pub use export::{ExportOptions, FileExport};
+pub use normalize::{Newline, Normalizer, Rules as NormalizeRules, UnicodeForm};
+pub use policy::MimeAllowList;
+#[cfg(feature = "server-warp")]
+pub use scan::Scanner;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,6 +114,7 @@ mod tests {
                 filename: String::from("baz.txt"),
                 content_type: String::from("text/plain"),
                 bytes: Bytes::from_static(b"Baz is a text file with this content."),
+                headers: HeaderMap::new(),
             }),
         );
 
@@ -109,6 +150,7 @@ mod tests {
             filename: String::from("file.txt"),
             content_type: String::from("text/plain"),
             bytes: b"this is the content of the file."[..].into(),
+            headers: HeaderMap::new(),
         };
 
         let field = Field::File(file.clone());
@@ -121,6 +163,7 @@ mod tests {
             filename: String::from("file.txt"),
             content_type: String::from("text/plain"),
             bytes: b"this is the content of the file."[..].into(),
+            headers: HeaderMap::new(),
         };
 
         let field = Field::File(file);
@@ -147,6 +190,7 @@ mod tests {
             filename: String::from("file.txt"),
             content_type: String::from("text/plain"),
             bytes: b"this is the content of the file."[..].into(),
+            headers: HeaderMap::new(),
         };
 
         let field = Field::File(file.clone());
@@ -159,6 +203,7 @@ mod tests {
             filename: String::from("file.txt"),
             content_type: String::from("text/plain"),
             bytes: b"this is the content of the file."[..].into(),
+            headers: HeaderMap::new(),
         };
 
         let field = Field::File(file);
@@ -265,6 +310,24 @@ mod tests {
         block_on(temp.unwrap()).unwrap()
     }
 
+    /// Sends a multipart body through `form_filter`.
+    #[cfg(feature = "server-warp")]
+    fn mock_filter_request(boundary: &str, body: &[u8]) -> Result<Form, Rejection> {
+        let filter = form_filter();
+
+        let result = warp::test::request()
+            .method("POST")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .header("Content-Length", format!("{}", body.len()))
+            .body(body)
+            .filter(&filter);
+
+        block_on(result)
+    }
+
     #[test]
     #[cfg(feature = "server-warp")]
     fn multipart_try_from_no_files() {
@@ -274,6 +337,7 @@ mod tests {
         let result = mock_warp_request(&boundary, &body);
 
         assert_eq!(form, result);
+        assert_eq!(mock_filter_request(&boundary, &body).unwrap(), result);
     }
 
     #[test]
@@ -285,6 +349,100 @@ mod tests {
         let result = mock_warp_request(&boundary, &body);
 
         assert_eq!(form, result);
+        assert_eq!(mock_filter_request(&boundary, &body).unwrap(), result);
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn multipart_bytes_round_trip() {
+        let (boundary, form) = mock_form(true);
+        let body = form.to_multipart_bytes(boundary.as_bytes());
+
+        let result = Form::from_multipart_bytes(&body, boundary.as_bytes()).unwrap();
+
+        assert_eq!(form, result);
+    }
+
+    #[test]
+    fn multipart_part_headers_round_trip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-id", "<scan-1@example.com>".parse().unwrap());
+        headers.append("x-checksum", "sha256=abc".parse().unwrap());
+        headers.append("x-checksum", "md5=def".parse().unwrap());
+
+        let mut form = Form::new();
+        form.insert(
+            "scan",
+            Field::File(FormFile {
+                filename: String::from("scan.pdf"),
+                content_type: String::from("application/pdf"),
+                bytes: Bytes::from_static(b"%PDF-1.4"),
+                headers,
+            }),
+        );
+        let body = form.to_multipart_bytes(b"headerboundary");
+
+        let result = Form::from_multipart_bytes(&body, b"headerboundary").unwrap();
+        assert_eq!(form, result);
+        #[cfg(feature = "server-warp")]
+        assert_eq!(form, mock_filter_request("headerboundary", &body).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn form_filter_keeps_part_headers() {
+        let (boundary, form) = mock_form(true);
+        let body = form.to_multipart_bytes(boundary.as_bytes());
+        assert_eq!(mock_filter_request(&boundary, &body).unwrap(), form);
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--b\r\nContent-Disposition: form-data; name=\"cv\"; ");
+        body.extend_from_slice(b"filename=\"cv.pdf\"\r\nContent-Type: application/pdf\r\n");
+        body.extend_from_slice(b"X-Original-Name: r\xc3\xa9sum\xc3\xa9.pdf\r\n");
+        body.extend_from_slice(b"X-Latin-1: caf\xe9\r\n");
+        body.extend_from_slice(b"Content-ID: <cv@example.com>\r\n\r\n%PDF\r\n--b--");
+
+        let form = mock_filter_request("b", &body).unwrap();
+        let file = form.get("cv").and_then(Field::as_file).unwrap();
+        assert_eq!(file.filename, "cv.pdf");
+        assert_eq!(file.bytes, Bytes::from_static(b"%PDF"));
+        assert_eq!(
+            file.headers.get("x-original-name").unwrap().as_bytes(),
+            "résumé.pdf".as_bytes()
+        );
+        assert_eq!(
+            file.headers.get("x-latin-1").unwrap().as_bytes(),
+            b"caf\xe9"
+        );
+        assert_eq!(file.headers.get("content-id").unwrap(), "<cv@example.com>");
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn multipart_try_from_nested_mixed() {
+        let boundary = "------mockboundaryvalue";
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--------mockboundaryvalue\r\n");
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"files\"\r\n");
+        body.extend_from_slice(b"Content-Type: multipart/mixed; boundary=nested\r\n\r\n");
+        body.extend_from_slice(b"--nested\r\nContent-Disposition: file; filename=\"a.txt\"\r\n");
+        body.extend_from_slice(b"Content-Type: text/plain\r\n\r\nfirst\r\n");
+        body.extend_from_slice(b"--nested\r\nContent-Disposition: file; filename=\"b.bin\"\r\n");
+        body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n\x00\xff\r\n");
+        body.extend_from_slice(b"--nested--\r\n--------mockboundaryvalue--");
+
+        let result = mock_warp_request(boundary, &body);
+
+        assert_eq!(result.len(), 2);
+        let files = result.files("files");
+        assert_eq!(files[0].filename, "a.txt");
+        assert_eq!(files[0].bytes, Bytes::from_static(b"first"));
+        assert_eq!(files[1].content_type, "application/octet-stream");
+        assert_eq!(files[1].bytes, Bytes::from_static(b"\x00\xff"));
+        assert_eq!(
+            result,
+            Form::from_multipart_bytes(&body, boundary.as_bytes()).unwrap()
+        );
     }
 
     #[test]
@@ -367,6 +525,164 @@ mod tests {
         assert_eq!(block_on(req).unwrap(), multipart);
     }
 
+    #[test]
+    fn form_from_url_encoded() {
+        let form = Form::from_url_encoded("q=hello+world&page=2&q=last%21").unwrap();
+
+        let mut expected = Form::new();
+        expected.insert("q", Field::Text(String::from("last!")));
+        expected.insert("page", Field::Text(String::from("2")));
+        assert_eq!(form, expected);
+    }
+
+    #[test]
+    fn merge_query_precedence() {
+        let mut query = Form::new();
+        query.insert("foo", Field::Text(String::from("query")));
+        query.insert("bar", Field::Text(String::from("query")));
+        let mut body = Form::new();
+        body.insert("foo", Field::Text(String::from("body")));
+        body.insert("baz", Field::Text(String::from("body")));
+
+        let form = Form::merge_query(
+            Form::from_url_encoded(&query.to_url_encoded().unwrap()).unwrap(),
+            Form::from_url_encoded(&body.to_url_encoded().unwrap()).unwrap(),
+            Precedence::Query,
+        );
+        assert_eq!(form.get("foo").and_then(Field::as_text), Some("query"));
+        assert_eq!(form.len(), 3);
+
+        let form = Form::merge_query(query, body, Precedence::Body);
+        assert_eq!(form.get("foo").and_then(Field::as_text), Some("body"));
+        assert_eq!(form.len(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn warp_query_filter() {
+        let (_, form) = mock_form(false);
+        let filter = query_filter();
+        let req = warp::test::request()
+            .method("GET")
+            .path(&format!("/search?{}", form.to_url_encoded().unwrap()))
+            .filter(&filter);
+        assert_eq!(block_on(req).unwrap(), form);
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn warp_query_filter_no_query_is_empty() {
+        let filter = query_filter();
+        let req = warp::test::request()
+            .method("GET")
+            .path("/search")
+            .filter(&filter);
+        assert_eq!(block_on(req).unwrap(), Form::new());
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn warp_query_and_form_filter_without_body() {
+        let filter = query_and_form_filter(Precedence::Body);
+        let req = warp::test::request()
+            .method("GET")
+            .path("/unsubscribe?email=foo%40example.com")
+            .filter(&filter);
+        let form = block_on(req).unwrap();
+        assert_eq!(
+            form.get("email").and_then(Field::as_text),
+            Some("foo@example.com")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn warp_query_and_form_filter_with_body() {
+        let (boundary, multipart) = mock_form(true);
+        let filter = query_and_form_filter(Precedence::Query);
+        let req = warp::test::request()
+            .method("POST")
+            .path("/form?foo=from+query&extra=1")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(multipart.to_multipart_bytes(boundary.as_bytes()))
+            .filter(&filter);
+        let form = block_on(req).unwrap();
+        assert_eq!(form.get("foo").and_then(Field::as_text), Some("from query"));
+        assert_eq!(form.get("extra").and_then(Field::as_text), Some("1"));
+        assert_eq!(form.get("baz"), multipart.get("baz"));
+        assert_eq!(form.len(), multipart.len() + 1);
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn warp_normalized_form_filter() {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("  Jane\u{200B}  Doe ")));
+        let filter = normalized_form_filter(Normalizer::new().default_rules(NormalizeRules::all()));
+        let req = warp::test::request()
+            .method("POST")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(form.to_url_encoded().unwrap().as_bytes())
+            .filter(&filter);
+        let result = block_on(req).unwrap();
+        assert_eq!(
+            result.get("name").and_then(Field::as_text),
+            Some("Jane Doe")
+        );
+    }
+
+    #[cfg(feature = "server-warp")]
+    struct BazScanner;
+
+    #[cfg(feature = "server-warp")]
+    impl Scanner for BazScanner {
+        fn scan<'a>(
+            &'a self,
+            file: &'a FormFile,
+        ) -> futures::future::BoxFuture<'a, Result<scan::Verdict, scan::Error>> {
+            let verdict = if file.bytes.starts_with(b"Baz") {
+                scan::Verdict::Infected(String::from("Baz-Signature"))
+            } else {
+                scan::Verdict::Clean
+            };
+            Box::pin(async move { Ok(verdict) })
+        }
+    }
+
+    #[cfg(feature = "server-warp")]
+    fn scanned_request(with_files: bool) -> Result<Form, Rejection> {
+        let (boundary, multipart) = mock_form(with_files);
+        let filter = scanning_form_filter(Arc::new(BazScanner));
+        let req = warp::test::request()
+            .method("POST")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(multipart.to_multipart_bytes(boundary.as_bytes()))
+            .filter(&filter);
+        block_on(req)
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn scanning_form_filter_clean() {
+        let (_, expected) = mock_form(false);
+        assert_eq!(scanned_request(false).unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn scanning_form_filter_rejects_infected() {
+        let rejection = scanned_request(true).unwrap_err();
+        let status = Status::<String>::recover(rejection).expect("rejection should be a Status");
+        assert_eq!(status.code(), &StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(status.message().unwrap().contains("baz.txt"));
+    }
+
     #[test]
     fn test_field_as_fromstr() {
         let field = Field::Text("12".to_string());
@@ -383,6 +699,7 @@ mod tests {
             filename: "test.txt".to_string(),
             content_type: "text/plain".to_string(),
             bytes: b"12".as_ref().into(),
+            headers: HeaderMap::new(),
         });
 
         let err = field
@@ -395,6 +712,41 @@ mod tests {
             panic!("Unexpected error: {:?}", err);
         }
     }
+
+    #[cfg(feature = "proptest")]
+    mod round_trip {
+        use super::*;
+        #[cfg(feature = "server-warp")]
+        use crate::arbitrary::multipart_form;
+        use crate::arbitrary::text_form;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn url_encoded(form in text_form()) {
+                let encoded = form.to_url_encoded().unwrap();
+                prop_assert_eq!(Form::from_url_encoded(&encoded).unwrap(), form);
+            }
+
+            #[test]
+            #[cfg(feature = "server-warp")]
+            fn multipart(form in multipart_form()) {
+                let boundary = "proptest-boundary-7c0b5e2f";
+                let body = form.to_multipart_bytes(boundary.as_bytes());
+                prop_assert_eq!(mock_filter_request(boundary, &body).unwrap(), form);
+            }
+        }
+    }
+}
+
+/// Determines which source wins when a query string and a request body both
+/// contain a field with the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precedence {
+    /// Values from the query string overwrite values from the body.
+    Query,
+    /// Values from the body overwrite values from the query string.
+    Body,
 }
 
 #[derive(Debug)]
@@ -405,6 +757,19 @@ pub enum Error {
     NotFile,
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::ParseField(msg) => write!(f, "invalid field: {}", msg),
+            Error::ParseForm(msg) => write!(f, "invalid form: {}", msg),
+            Error::NotText => write!(f, "field is not text"),
+            Error::NotFile => write!(f, "field is not a file"),
+        }
+    }
+}
+
+impl StdError for Error {}
+
 /// Represents a single file submitted through a form
 #[derive(Clone, Debug, PartialEq)]
 pub struct FormFile {
@@ -416,6 +781,28 @@ pub struct FormFile {
     ///
     /// These bytes should be interpreted based on the file's `content_type`.
     pub bytes: Bytes,
+    /// Any other headers sent with the file's multipart part, such as
+    /// `Content-ID` or `Content-Transfer-Encoding`. `Content-Disposition` and
+    /// `Content-Type` are represented by `filename` and `content_type`.
+    pub headers: HeaderMap,
+}
+
+impl FormFile {
+    /// Creates a file with no extra part headers.
+    pub fn new(filename: String, content_type: String, bytes: Bytes) -> Self {
+        FormFile {
+            filename,
+            content_type,
+            bytes,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Sets the extra part headers of the file.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
 }
 
 /// Represents the contents of a single field of the submitted form.
@@ -480,6 +867,7 @@ impl Field {
             filename,
             content_type,
             bytes: content,
+            headers: HeaderMap::new(),
         });
 
         Ok((name, field))
@@ -533,7 +921,13 @@ impl Field {
 }
 
 /// Represents the entire contents of a submitted form.
-#[derive(Debug, Default, PartialEq)]
+///
+/// Each name holds a single field. The files of a nested `multipart/mixed`
+/// part are therefore not kept under the part's name, but under `name[0]`,
+/// `name[1]`, and so on, one per nested part; use `Form::files` to get them
+/// together. Parsing fails if the form also has an ordinary field with one of
+/// those names.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Form(HashMap<String, Field>);
 
 impl IntoIterator for Form {
@@ -635,6 +1029,30 @@ impl Form {
     }
 
     // # Conversions
+    /// Parses a URL encoded string, such as a request's query string or an
+    /// `application/x-www-form-urlencoded` body, into a `Form`.
+    ///
+    /// Decoding follows the same rules as `form_filter`: `+` is decoded as a
+    /// space and, if a field name appears more than once, the last value is
+    /// kept.
+    pub fn from_url_encoded(input: &str) -> Result<Form, Error> {
+        serde_urlencoded::from_str::<HashMap<String, String>>(input)
+            .map(Form::from)
+            .map_err(|e| Error::ParseForm(e.to_string()))
+    }
+
+    /// Combines the fields parsed from a query string with those parsed from
+    /// a request body. When both contain a field with the same name, the
+    /// value from the source indicated by `precedence` is kept.
+    pub fn merge_query(query: Form, body: Form, precedence: Precedence) -> Form {
+        let (mut form, other) = match precedence {
+            Precedence::Query => (body, query),
+            Precedence::Body => (query, body),
+        };
+        form.extend(other.into_iter());
+        form
+    }
+
     /// Returns the `Form` to a URL encoded format, suitable for `GET` requests
     /// or the body of a `Content-Type: application/x-www-form-urlencoded` `POST` request.
     pub fn to_url_encoded(&self) -> Result<String, String> {
@@ -675,6 +1093,12 @@ impl Form {
                     buf.extend_from_slice(file.filename.as_bytes());
                     buf.extend_from_slice(b"\"\r\nContent-type: ");
                     buf.extend_from_slice(file.content_type.as_bytes());
+                    for (name, value) in file.headers.iter() {
+                        buf.extend_from_slice(b"\r\n");
+                        buf.extend_from_slice(name.as_str().as_bytes());
+                        buf.extend_from_slice(b": ");
+                        buf.extend_from_slice(value.as_bytes());
+                    }
                     buf.extend_from_slice(b"\r\n\r\n");
                     buf.extend_from_slice(&file.bytes);
                 }
@@ -690,13 +1114,19 @@ impl Form {
         buf
     }
 
-    #[cfg(feature = "server-warp")]
+    #[cfg(all(test, feature = "server-warp"))]
     /// Attempts to consume a Warp `FormData` stream and return a `Form` built
     /// from its contents.
     ///
+    /// Warp does not expose a part's headers other than its name, filename
+    /// and content type, so `form_filter` parses the request body itself to
+    /// keep them. This is only used by the tests, to check that both parse
+    /// forms the same way otherwise.
+    ///
     /// Requires `features = "server-warp"`.
     async fn try_from_formdata(mut data: FormData) -> Result<Self, Status<String>> {
         let mut form = Form::new();
+        let mut expanded = HashSet::new();
 
         while let Some(part) = data.next().await {
             match part {
@@ -707,14 +1137,82 @@ impl Form {
                     ))
                 }
                 Ok(part) => {
-                    let (name, field) = Field::try_from_async(part).await?;
-                    form.insert(&name, field)
+                    let (fields, nested) = match part.content_type().map(String::from) {
+                        Some(content_type)
+                            if part.filename().is_none() && multipart::is_mixed(&content_type) =>
+                        {
+                            let name = part.name().to_string();
+                            let content =
+                                Field::buf_to_bytes(part.stream()).await.map_err(|e| {
+                                    Status::with_message(
+                                        StatusCode::INTERNAL_SERVER_ERROR,
+                                        e.to_string(),
+                                    )
+                                })?;
+                            let fields = multipart::expand_mixed(&name, &content_type, &content)
+                                .map_err(|e| {
+                                    Status::with_message(StatusCode::BAD_REQUEST, e.to_string())
+                                })?;
+                            (fields, true)
+                        }
+                        _ => (vec![Field::try_from_async(part).await?], false),
+                    };
+                    multipart::insert_fields(&mut form, &mut expanded, fields, nested).map_err(
+                        |e| Status::with_message(StatusCode::BAD_REQUEST, e.to_string()),
+                    )?;
                 }
             };
         }
 
         Ok(form)
     }
+
+    #[cfg(feature = "server-warp")]
+    /// Attempts to parse a `multipart/form-data` request body and return a
+    /// `Form` built from its contents, keeping each file part's headers.
+    ///
+    /// If a `scanner` is given, every file is passed through it and the first
+    /// file that is rejected causes an error.
+    ///
+    /// Requires `features = "server-warp"`.
+    async fn try_from_multipart(
+        content_type: &str,
+        body: Bytes,
+        scanner: Option<&dyn Scanner>,
+    ) -> Result<Self, Status<String>> {
+        let boundary = match multipart::boundary(content_type) {
+            Some(boundary) if content_type_is(content_type, "multipart/form-data") => boundary,
+            _ => {
+                return Err(Status::with_message(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("expected multipart/form-data, got {}", content_type),
+                ))
+            }
+        };
+
+        let form = Form::from_multipart_bytes(&body, boundary.as_bytes())
+            .map_err(|e| Status::with_message(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        if let Some(scanner) = scanner {
+            for (_, field) in form.iter() {
+                if let Field::File(file) = field {
+                    scan::check(scanner, file).await?;
+                }
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+#[cfg(feature = "server-warp")]
+/// Returns whether `content_type`'s essence, ignoring parameters and case, is
+/// `essence`.
+fn content_type_is(content_type: &str, essence: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(essence))
 }
 
 impl From<HashMap<String, String>> for Form {
@@ -759,14 +1257,95 @@ impl Reject for RejectionWrapper {}
 ///
 /// Requires `features = "server-warp"`.
 pub fn form_filter() -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
+    form_filter_with_scanner(None)
+}
+
+#[cfg(feature = "server-warp")]
+/// Returns a `Filter` that behaves like `form_filter`, but passes every
+/// uploaded file through `scanner` while the form is parsed. Requests
+/// containing an infected file are rejected with a
+/// `422 Unprocessable Entity` `Status`, and files that cannot be scanned
+/// cause a `500 Internal Server Error`.
+///
+/// Requires `features = "server-warp"`.
+pub fn scanning_form_filter(
+    scanner: Arc<dyn Scanner>,
+) -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
+    form_filter_with_scanner(Some(scanner))
+}
+
+#[cfg(feature = "server-warp")]
+/// The largest `multipart/form-data` body `form_filter` accepts, the same as
+/// Warp's own multipart filter.
+const MULTIPART_LIMIT: u64 = 1024 * 1024 * 2;
+
+#[cfg(feature = "server-warp")]
+fn form_filter_with_scanner(
+    scanner: Option<Arc<dyn Scanner>>,
+) -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
     warp::filters::body::form()
         .map(|f: HashMap<String, String>| Form::from(f))
-        .or(
-            warp::filters::multipart::form().and_then(|f: FormData| async move {
-                Form::try_from_formdata(f)
-                    .await
-                    .map_err(|e| warp::reject::custom(e))
-            }),
-        )
+        .or(warp::header::<String>("content-type")
+            .and(warp::body::content_length_limit(MULTIPART_LIMIT))
+            .and(warp::body::bytes())
+            .and_then(move |content_type: String, body: Bytes| {
+                let scanner = scanner.clone();
+                async move {
+                    Form::try_from_multipart(&content_type, body, scanner.as_deref())
+                        .await
+                        .map_err(Rejection::from)
+                }
+            }))
         .unify()
 }
+
+#[cfg(feature = "server-warp")]
+/// Returns a `Filter` that behaves like `form_filter`, but normalizes the
+/// text fields of the parsed `Form` with `normalizer` before passing it on.
+///
+/// Requires `features = "server-warp"`.
+pub fn normalized_form_filter(
+    normalizer: Normalizer,
+) -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
+    let normalizer = Arc::new(normalizer);
+    form_filter().map(move |mut form: Form| {
+        form.normalize(&normalizer);
+        form
+    })
+}
+
+#[cfg(feature = "server-warp")]
+/// Returns a `Filter` that parses the request's query string into a `Form`,
+/// using the same decoding as URL-encoded bodies in `form_filter`. A request
+/// without a query string produces an empty `Form`.
+///
+/// Requires `features = "server-warp"`.
+pub fn query_filter() -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
+    warp::query::<HashMap<String, String>>().map(|f: HashMap<String, String>| Form::from(f))
+}
+
+#[cfg(feature = "server-warp")]
+/// Returns a `Filter` that parses both the query string and the request body
+/// and merges them into a single `Form`, keeping the value from the source
+/// indicated by `precedence` when a field appears in both.
+///
+/// Requests without a `Content-Type` header, such as most `GET` requests, are
+/// treated as having an empty body.
+///
+/// Requires `features = "server-warp"`.
+pub fn query_and_form_filter(
+    precedence: Precedence,
+) -> impl Filter<Extract = (Form,), Error = Rejection> + Clone {
+    let no_body = warp::header::optional::<String>("content-type").and_then(
+        |content_type: Option<String>| async move {
+            match content_type {
+                None => Ok(Form::new()),
+                Some(_) => Err(warp::reject::not_found()),
+            }
+        },
+    );
+
+    query_filter()
+        .and(form_filter().or(no_body).unify())
+        .map(move |query: Form, body: Form| Form::merge_query(query, body, precedence))
+}
@@ -0,0 +1,475 @@
+use crate::{Error, Field, Form, FormFile, HeaderMap};
+use bytes::Bytes;
+use http::header::{HeaderName, HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIXED: &[u8] = b"--AaB03x\r\n\
+Content-Disposition: form-data; name=\"submit-name\"\r\n\
+\r\n\
+Larry\r\n\
+--AaB03x\r\n\
+Content-Disposition: form-data; name=\"files\"\r\n\
+Content-Type: multipart/mixed; boundary=BbC04y\r\n\
+\r\n\
+--BbC04y\r\n\
+Content-Disposition: file; filename=\"file1.txt\"\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+... contents of file1.txt ...\r\n\
+--BbC04y\r\n\
+Content-Disposition: file; filename=\"file2.gif\"\r\n\
+Content-Type: image/gif\r\n\
+Content-Transfer-Encoding: binary\r\n\
+\r\n\
+GIF89a\x00\x01\r\n\
+--BbC04y--\r\n\
+--AaB03x--\r\n";
+
+    #[test]
+    fn parse_parts_and_headers() {
+        let parts = parse(&Bytes::from_static(MIXED), b"AaB03x").unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name(), Some(String::from("submit-name")));
+        assert_eq!(parts[0].body, Bytes::from_static(b"Larry"));
+        assert_eq!(
+            parts[1].header("content-type").as_deref(),
+            Some("multipart/mixed; boundary=BbC04y")
+        );
+    }
+
+    #[test]
+    fn nested_mixed_parts_become_indexed_files() {
+        let form = Form::from_multipart_bytes(MIXED, b"AaB03x").unwrap();
+        assert_eq!(form.len(), 3);
+        assert_eq!(
+            form.get("submit-name"),
+            Some(&Field::Text(String::from("Larry")))
+        );
+        assert_eq!(
+            form.get("files[1]"),
+            Some(&Field::File(FormFile {
+                filename: String::from("file2.gif"),
+                content_type: String::from("image/gif"),
+                bytes: Bytes::from_static(b"GIF89a\x00\x01"),
+                headers: {
+                    let mut headers = HeaderMap::new();
+                    headers.insert(
+                        "content-transfer-encoding",
+                        HeaderValue::from_static("binary"),
+                    );
+                    headers
+                },
+            }))
+        );
+
+        let names: Vec<&str> = form
+            .files("files")
+            .into_iter()
+            .map(|file| file.filename.as_str())
+            .collect();
+        assert_eq!(names, vec!["file1.txt", "file2.gif"]);
+    }
+
+    fn clashes(result: Result<Form, Error>) -> bool {
+        matches!(result, Err(Error::ParseForm(msg)) if msg.contains("clashes"))
+    }
+
+    #[test]
+    fn names_clashing_with_nested_files_are_errors() {
+        let literal = b"--AaB03x\r\n\
+Content-Disposition: form-data; name=\"files[0]\"; filename=\"evil.txt\"\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+not from the nested part\r\n";
+
+        // Before the nested part
+        let mut body = literal.to_vec();
+        body.extend_from_slice(MIXED);
+        assert!(clashes(Form::from_multipart_bytes(&body, b"AaB03x")));
+
+        // After the nested part
+        let mut body = MIXED[..MIXED.len() - b"--AaB03x--\r\n".len()].to_vec();
+        body.extend_from_slice(literal);
+        body.extend_from_slice(b"--AaB03x--\r\n");
+        assert!(clashes(Form::from_multipart_bytes(&body, b"AaB03x")));
+    }
+
+    #[test]
+    fn quoted_parameters() {
+        let part = Part {
+            headers: vec![(
+                String::from("Content-Disposition"),
+                Bytes::from_static(b"form-data; name=\"a;b\"; filename=\"say \\\"hi\\\".txt\""),
+            )],
+            body: Bytes::new(),
+        };
+        assert_eq!(part.name(), Some(String::from("a;b")));
+        assert_eq!(part.filename(), Some(String::from("say \"hi\".txt")));
+        assert_eq!(
+            boundary("multipart/mixed; charset=utf-8; Boundary=\"a b\""),
+            Some(String::from("a b"))
+        );
+    }
+
+    #[test]
+    fn malformed_bodies_are_errors() {
+        for body in &[
+            &b""[..],
+            b"no delimiter",
+            b"--AaB03x\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nunterminated",
+            b"--AaB03x\r\nno header terminator",
+            b"--AaB03x\r\nContent-Disposition: form-data\r\n\r\nno name\r\n--AaB03x--",
+        ] {
+            assert!(Form::from_multipart_bytes(body, b"AaB03x").is_err());
+        }
+    }
+}
+
+/// A single part of a multipart body.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Part {
+    /// The part's headers, in order, with names as they were sent and values
+    /// as raw bytes, which need not be UTF-8.
+    pub headers: Vec<(String, Bytes)>,
+    pub body: Bytes,
+}
+
+impl Part {
+    /// Returns the value of the first header called `name`, ignoring case.
+    /// Bytes that are not UTF-8 are replaced.
+    pub fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| String::from_utf8_lossy(value))
+    }
+
+    /// Returns the part's headers other than `Content-Disposition` and
+    /// `Content-Type`, which become a file's `filename` and `content_type`.
+    /// Headers that cannot be represented in a `HeaderMap` are left out.
+    pub fn extra_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(name) if name != CONTENT_DISPOSITION && name != CONTENT_TYPE => name,
+                _ => continue,
+            };
+            if let Ok(value) = HeaderValue::from_maybe_shared(value.clone()) {
+                headers.append(name, value);
+            }
+        }
+        headers
+    }
+
+    fn disposition_param(&self, name: &str) -> Option<String> {
+        param(&self.header("content-disposition")?, name)
+    }
+
+    /// Returns the `name` parameter of the part's `Content-Disposition`.
+    pub fn name(&self) -> Option<String> {
+        self.disposition_param("name")
+    }
+
+    /// Returns the `filename` parameter of the part's `Content-Disposition`.
+    pub fn filename(&self) -> Option<String> {
+        self.disposition_param("filename")
+    }
+
+    /// Returns the part's `Content-Type`, if it has one.
+    pub fn content_type(&self) -> Option<Cow<'_, str>> {
+        self.header("content-type")
+    }
+}
+
+/// Splits a header value such as `form-data; name="foo"` on semicolons that
+/// are not inside a quoted string.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+/// Returns the value of the parameter `name` in a header value, removing any
+/// quotes and escapes.
+fn param(value: &str, name: &str) -> Option<String> {
+    split_params(value).into_iter().skip(1).find_map(|param| {
+        let mut pair = param.splitn(2, '=');
+        let key = pair.next()?.trim();
+        let val = pair.next()?.trim();
+        if !key.eq_ignore_ascii_case(name) {
+            return None;
+        }
+
+        match val.strip_prefix('"').and_then(|val| val.strip_suffix('"')) {
+            None => Some(val.to_string()),
+            Some(quoted) => {
+                let mut unescaped = String::with_capacity(quoted.len());
+                let mut chars = quoted.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => unescaped.extend(chars.next()),
+                        _ => unescaped.push(c),
+                    }
+                }
+                Some(unescaped)
+            }
+        }
+    })
+}
+
+/// Returns the `boundary` parameter of a multipart content type.
+pub(crate) fn boundary(content_type: &str) -> Option<String> {
+    param(content_type, "boundary").filter(|boundary| !boundary.is_empty())
+}
+
+/// Indicates whether `content_type` is `multipart/mixed`.
+pub(crate) fn is_mixed(content_type: &str) -> bool {
+    split_params(content_type)[0]
+        .trim()
+        .eq_ignore_ascii_case("multipart/mixed")
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn malformed(msg: &str) -> Error {
+    Error::ParseForm(format!("malformed multipart body: {}", msg))
+}
+
+/// Parses the headers of a part, unfolding continuation lines. Values are
+/// kept as bytes, as clients may send them in any encoding.
+fn parse_headers(block: &[u8]) -> Result<Vec<(String, Bytes)>, Error> {
+    let mut headers: Vec<(String, Vec<u8>)> = Vec::new();
+    let lines = block
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty());
+    for line in lines {
+        if line[0] == b' ' || line[0] == b'\t' {
+            let (_, value) = headers
+                .last_mut()
+                .ok_or_else(|| malformed("continuation line without a header"))?;
+            value.push(b' ');
+            value.extend_from_slice(line.trim_ascii());
+            continue;
+        }
+
+        let colon = line
+            .iter()
+            .position(|byte| *byte == b':')
+            .ok_or_else(|| malformed("header without a colon"))?;
+        let name = String::from_utf8_lossy(line[..colon].trim_ascii());
+        if name.is_empty() {
+            return Err(malformed("header without a name"));
+        }
+        headers.push((name.into_owned(), line[colon + 1..].trim_ascii().to_vec()));
+    }
+    Ok(headers
+        .into_iter()
+        .map(|(name, value)| (name, Bytes::from(value)))
+        .collect())
+}
+
+/// Parses a multipart body with the given boundary into its parts. Any
+/// preamble before the first delimiter and epilogue after the last one is
+/// ignored.
+pub(crate) fn parse(body: &Bytes, boundary: &[u8]) -> Result<Vec<Part>, Error> {
+    let mut delimiter = b"\r\n--".to_vec();
+    delimiter.extend_from_slice(boundary);
+
+    // The first delimiter does not need to be preceded by a line break
+    let mut pos = if body.starts_with(&delimiter[2..]) {
+        delimiter.len() - 2
+    } else {
+        find(body, &delimiter).ok_or_else(|| malformed("no opening delimiter"))? + delimiter.len()
+    };
+
+    let mut parts = Vec::new();
+    loop {
+        let rest = &body[pos..];
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+
+        // Transport padding may follow the delimiter
+        let padding = rest
+            .iter()
+            .take_while(|byte| **byte == b' ' || **byte == b'\t')
+            .count();
+        if !rest[padding..].starts_with(b"\r\n") {
+            return Err(malformed("delimiter is not followed by a line break"));
+        }
+        let start = pos + padding + 2;
+        let rest = &body[start..];
+
+        let (headers, body_start) = if rest.starts_with(b"\r\n") {
+            (Vec::new(), start + 2)
+        } else {
+            let end = find(rest, b"\r\n\r\n").ok_or_else(|| malformed("unterminated headers"))?;
+            (parse_headers(&rest[..end])?, start + end + 4)
+        };
+
+        let len = find(&body[body_start..], &delimiter)
+            .ok_or_else(|| malformed("no closing delimiter"))?;
+        parts.push(Part {
+            headers,
+            body: body.slice(body_start..body_start + len),
+        });
+        pos = body_start + len + delimiter.len();
+    }
+}
+
+/// Converts a nested `multipart/mixed` part into one file per nested part,
+/// named `name[0]`, `name[1]`, and so on. Nested parts without a
+/// `Content-Type` are `text/plain`, as in RFC 2046.
+pub(crate) fn expand_mixed(
+    name: &str,
+    content_type: &str,
+    body: &Bytes,
+) -> Result<Vec<(String, Field)>, Error> {
+    let boundary =
+        boundary(content_type).ok_or_else(|| malformed("multipart/mixed part has no boundary"))?;
+    let fields = parse(body, boundary.as_bytes())?
+        .into_iter()
+        .enumerate()
+        .map(|(idx, part)| {
+            let file = FormFile {
+                filename: part.filename().unwrap_or_default(),
+                content_type: part
+                    .content_type()
+                    .map_or_else(|| String::from("text/plain"), Cow::into_owned),
+                headers: part.extra_headers(),
+                bytes: part.body,
+            };
+            (format!("{}[{}]", name, idx), Field::File(file))
+        })
+        .collect();
+    Ok(fields)
+}
+
+/// Adds the fields of one top-level part to `form`. `nested` is whether they
+/// were expanded from a `multipart/mixed` part, and `expanded` collects the
+/// names of all such fields.
+///
+/// Expanded files are named `name[0]`, `name[1]`, and so on, which a client
+/// could also send as ordinary field names. Any field that shares its name
+/// with an expanded file is an error, rather than replacing it or being
+/// mistaken for one.
+pub(crate) fn insert_fields(
+    form: &mut Form,
+    expanded: &mut HashSet<String>,
+    fields: Vec<(String, Field)>,
+    nested: bool,
+) -> Result<(), Error> {
+    for (name, field) in fields {
+        let clashes = if nested {
+            form.get(&name).is_some()
+        } else {
+            expanded.contains(&name)
+        };
+        if clashes {
+            return Err(Error::ParseForm(format!(
+                "field {} clashes with a file from a nested multipart/mixed part",
+                name
+            )));
+        }
+        if nested {
+            expanded.insert(name.clone());
+        }
+        form.insert(&name, field);
+    }
+    Ok(())
+}
+
+/// Converts a top-level `multipart/form-data` part into fields, and returns
+/// whether they were expanded from a nested `multipart/mixed` part.
+fn part_to_fields(part: Part) -> Result<(Vec<(String, Field)>, bool), Error> {
+    let name = part
+        .name()
+        .ok_or_else(|| malformed("part has no field name"))?;
+    let content_type = part.content_type().map(Cow::into_owned);
+
+    match (part.filename(), content_type) {
+        (None, Some(content_type)) if is_mixed(&content_type) => {
+            Ok((expand_mixed(&name, &content_type, &part.body)?, true))
+        }
+        (None, _) => {
+            let text = String::from_utf8(part.body.to_vec())
+                .map_err(|err| Error::ParseField(err.to_string()))?;
+            Ok((vec![(name, Field::Text(text))], false))
+        }
+        (Some(_), None) => Err(Error::ParseField(String::from(
+            "form field has filename but no content type",
+        ))),
+        (Some(filename), Some(content_type)) => {
+            let file = FormFile {
+                filename,
+                content_type,
+                headers: part.extra_headers(),
+                bytes: part.body,
+            };
+            Ok((vec![(name, Field::File(file))], false))
+        }
+    }
+}
+
+impl Form {
+    /// Parses a `multipart/form-data` body, the inverse of
+    /// `Form::to_multipart_bytes`.
+    ///
+    /// A part whose content type is `multipart/mixed`, which older clients
+    /// use to send several files under one field name, is expanded into one
+    /// `Field::File` per nested part, named `name[0]`, `name[1]`, and so on.
+    /// Use `Form::files` to collect them again. Any other field with one of
+    /// those names is an error.
+    pub fn from_multipart_bytes(body: &[u8], boundary: &[u8]) -> Result<Form, Error> {
+        let body = Bytes::copy_from_slice(body);
+        let mut form = Form::new();
+        let mut expanded = HashSet::new();
+        for part in parse(&body, boundary)? {
+            let (fields, nested) = part_to_fields(part)?;
+            insert_fields(&mut form, &mut expanded, fields, nested)?;
+        }
+        Ok(form)
+    }
+
+    /// Returns the files submitted under `name`: the field `name` itself, if
+    /// it is a file, followed by `name[0]`, `name[1]`, and so on, as created
+    /// from nested `multipart/mixed` parts.
+    pub fn files(&self, name: &str) -> Vec<&FormFile> {
+        let mut files: Vec<&FormFile> = self
+            .get(name)
+            .and_then(Field::as_file)
+            .into_iter()
+            .collect();
+        let mut idx = 0;
+        while let Some(field) = self.get(&format!("{}[{}]", name, idx)) {
+            files.extend(field.as_file());
+            idx += 1;
+        }
+        files
+    }
+}
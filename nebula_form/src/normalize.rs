@@ -0,0 +1,288 @@
+use crate::{Field, Form};
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_rules_change_nothing() {
+        let text = " \u{200B}Foo\r\n  bar\t";
+        assert_eq!(Rules::new().apply(text), text);
+    }
+
+    #[test]
+    fn trim() {
+        assert_eq!(Rules::new().trim().apply("  foo bar \n"), "foo bar");
+    }
+
+    #[test]
+    fn collapse_whitespace_keeps_line_breaks() {
+        let rules = Rules::new().collapse_whitespace();
+        assert_eq!(rules.apply("foo  \t bar\n\n  baz"), "foo bar\n\n baz");
+    }
+
+    #[test]
+    fn newlines_lf() {
+        let rules = Rules::new().newlines(Newline::Lf);
+        assert_eq!(rules.apply("a\r\nb\rc\nd"), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn newlines_crlf() {
+        let rules = Rules::new().newlines(Newline::CrLf);
+        assert_eq!(rules.apply("a\r\nb\rc\nd"), "a\r\nb\r\nc\r\nd");
+    }
+
+    #[test]
+    fn unicode_nfc() {
+        let rules = Rules::new().unicode(UnicodeForm::Nfc);
+        assert_eq!(rules.apply("e\u{0301}"), "\u{00E9}");
+        assert_eq!(rules.apply("\u{FB01}"), "\u{FB01}");
+    }
+
+    #[test]
+    fn unicode_nfkc() {
+        let rules = Rules::new().unicode(UnicodeForm::Nfkc);
+        assert_eq!(rules.apply("e\u{0301}"), "\u{00E9}");
+        assert_eq!(rules.apply("\u{FB01}"), "fi");
+    }
+
+    #[test]
+    fn strip_control_removes_zero_width() {
+        let rules = Rules::new().strip_control();
+        assert_eq!(
+            rules.apply("\u{FEFF}fo\u{200B}o\u{0007}\tbar\r\nbaz"),
+            "foo\tbar\r\nbaz"
+        );
+    }
+
+    #[test]
+    fn unicode_applies_after_stripping() {
+        let rules = Rules::new().strip_control().unicode(UnicodeForm::Nfc);
+        assert_eq!(rules.apply("e\u{200B}\u{0301}"), "\u{00E9}");
+    }
+
+    #[test]
+    fn all_rules() {
+        let text = "  Cafe\u{0301}\u{200D}  au\r\n lait \u{0000} ";
+        assert_eq!(Rules::all().apply(text), "Caf\u{00E9} au\n lait");
+    }
+
+    #[test]
+    fn form_uses_field_rules_then_default() {
+        let mut form = Form::new();
+        form.insert("email", Field::Text(String::from(" Foo@Example.com ")));
+        form.insert("message", Field::Text(String::from(" hi  there \r\n")));
+        form.insert("raw", Field::Text(String::from(" untouched ")));
+
+        let normalizer = Normalizer::new()
+            .default_rules(Rules::new().trim())
+            .field("message", Rules::new().collapse_whitespace())
+            .field("raw", Rules::new());
+        form.normalize(&normalizer);
+
+        assert_eq!(
+            form.get("email").and_then(Field::as_text),
+            Some("Foo@Example.com")
+        );
+        assert_eq!(
+            form.get("message").and_then(Field::as_text),
+            Some(" hi there \r\n")
+        );
+        assert_eq!(
+            form.get("raw").and_then(Field::as_text),
+            Some(" untouched ")
+        );
+    }
+}
+
+/// The line ending that `Rules::newlines` converts all line breaks into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Newline {
+    /// `\n`
+    Lf,
+    /// `\r\n`, as used by HTML form submissions.
+    CrLf,
+}
+
+/// The Unicode normalization form that `Rules::unicode` applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Canonical composition.
+    Nfc,
+    /// Compatibility composition. This also folds characters such as
+    /// ligatures and full-width letters into their plain equivalents.
+    Nfkc,
+}
+
+/// A set of normalization steps applied to the value of a text field.
+///
+/// Steps are applied in a fixed order: control character stripping, newline
+/// normalization, whitespace collapsing, trimming, and finally Unicode
+/// normalization, so that the result is always in the chosen form.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rules {
+    trim: bool,
+    collapse_whitespace: bool,
+    newlines: Option<Newline>,
+    unicode: Option<UnicodeForm>,
+    strip_control: bool,
+}
+
+impl Rules {
+    /// Creates an empty set of rules that leaves text unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a set of rules with every step enabled, using `\n` line
+    /// endings and NFC normalization.
+    pub fn all() -> Self {
+        Self::new()
+            .trim()
+            .collapse_whitespace()
+            .newlines(Newline::Lf)
+            .unicode(UnicodeForm::Nfc)
+            .strip_control()
+    }
+
+    /// Removes leading and trailing whitespace from the value.
+    pub fn trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    /// Replaces every run of whitespace other than line breaks with a
+    /// single space.
+    pub fn collapse_whitespace(mut self) -> Self {
+        self.collapse_whitespace = true;
+        self
+    }
+
+    /// Converts `\r\n`, `\r`, and `\n` line breaks into `newline`.
+    pub fn newlines(mut self, newline: Newline) -> Self {
+        self.newlines = Some(newline);
+        self
+    }
+
+    /// Applies the given Unicode normalization form.
+    pub fn unicode(mut self, form: UnicodeForm) -> Self {
+        self.unicode = Some(form);
+        self
+    }
+
+    /// Removes control characters other than tabs and line breaks, as well
+    /// as zero-width and other invisible formatting characters.
+    pub fn strip_control(mut self) -> Self {
+        self.strip_control = true;
+        self
+    }
+
+    /// Returns `text` with these rules applied.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        if self.strip_control {
+            text.retain(|c| !is_stripped_control(c));
+        }
+
+        if let Some(newline) = self.newlines {
+            let newline = match newline {
+                Newline::Lf => "\n",
+                Newline::CrLf => "\r\n",
+            };
+            text = text
+                .replace("\r\n", "\n")
+                .replace('\r', "\n")
+                .replace('\n', newline);
+        }
+
+        if self.collapse_whitespace {
+            let mut collapsed = String::with_capacity(text.len());
+            let mut in_whitespace = false;
+            for c in text.chars() {
+                if c.is_whitespace() && c != '\n' && c != '\r' {
+                    if !in_whitespace {
+                        collapsed.push(' ');
+                    }
+                    in_whitespace = true;
+                } else {
+                    collapsed.push(c);
+                    in_whitespace = false;
+                }
+            }
+            text = collapsed;
+        }
+
+        if self.trim {
+            text = text.trim().to_string();
+        }
+
+        // Last, as removing characters can leave text that is not normalized
+        match self.unicode {
+            None => text,
+            Some(UnicodeForm::Nfc) => text.nfc().collect(),
+            Some(UnicodeForm::Nfkc) => text.nfkc().collect(),
+        }
+    }
+}
+
+/// Indicates whether `Rules::strip_control` removes the character.
+fn is_stripped_control(c: char) -> bool {
+    match c {
+        '\t' | '\n' | '\r' => false,
+        // Zero-width space, non-joiner, joiner, word joiner, and BOM
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => true,
+        // Bidirectional formatting characters
+        '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => true,
+        c => c.is_control(),
+    }
+}
+
+/// Maps form fields to the `Rules` used to normalize their values.
+///
+/// Fields without their own rules use the default rules, if any are set.
+/// File fields are never modified.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Normalizer {
+    default: Option<Rules>,
+    fields: HashMap<String, Rules>,
+}
+
+impl Normalizer {
+    /// Creates a normalizer with no rules, which leaves every field unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the rules used for fields that do not have their own.
+    pub fn default_rules(mut self, rules: Rules) -> Self {
+        self.default = Some(rules);
+        self
+    }
+
+    /// Sets the rules used for the field with the given name.
+    pub fn field(mut self, name: &str, rules: Rules) -> Self {
+        self.fields.insert(name.to_string(), rules);
+        self
+    }
+
+    /// Returns the rules that apply to the field with the given name.
+    pub fn rules_for(&self, name: &str) -> Option<&Rules> {
+        self.fields.get(name).or(self.default.as_ref())
+    }
+}
+
+impl Form {
+    /// Normalizes the value of every text field in place, according to the
+    /// rules `normalizer` has for that field.
+    pub fn normalize(&mut self, normalizer: &Normalizer) {
+        for (name, field) in self.0.iter_mut() {
+            if let (Field::Text(txt), Some(rules)) = (field, normalizer.rules_for(name)) {
+                *txt = rules.apply(txt);
+            }
+        }
+    }
+}
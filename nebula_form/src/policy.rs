@@ -0,0 +1,100 @@
+use crate::FormFile;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeaderMap;
+
+    #[test]
+    fn empty_list_allows_nothing() {
+        assert!(!MimeAllowList::new().allows("text/plain"));
+    }
+
+    #[test]
+    fn exact_match_ignores_case_and_parameters() {
+        let list = MimeAllowList::new().allow("text/plain");
+        assert!(list.allows("text/plain"));
+        assert!(list.allows("Text/Plain; charset=utf-8"));
+        assert!(!list.allows("text/html"));
+    }
+
+    #[test]
+    fn wildcards() {
+        let list = MimeAllowList::new().allow("image/*");
+        assert!(list.allows("image/png"));
+        assert!(!list.allows("application/pdf"));
+        assert!(MimeAllowList::new().allow("*/*").allows("application/pdf"));
+    }
+
+    #[test]
+    fn file_content_type() {
+        let list = MimeAllowList::new().allow("application/pdf");
+        let file = FormFile {
+            filename: String::from("doc.pdf"),
+            content_type: String::from("application/pdf"),
+            bytes: b"%PDF"[..].into(),
+            headers: HeaderMap::new(),
+        };
+        assert!(file.is_allowed(&list));
+    }
+}
+
+/// Returns the `type/subtype` part of a content type, lowercased and with
+/// any parameters removed.
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// A list of the content types that are accepted for uploaded files.
+///
+/// Patterns are either exact types such as `application/pdf`, a whole
+/// top-level type such as `image/*`, or `*/*` for anything. An empty list
+/// allows nothing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MimeAllowList(Vec<String>);
+
+impl MimeAllowList {
+    /// Creates an empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern to the list.
+    pub fn allow(mut self, pattern: &str) -> Self {
+        self.0.push(essence(pattern));
+        self
+    }
+
+    /// Returns the patterns in the list.
+    pub fn patterns(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Indicates whether the given content type matches any pattern in the
+    /// list. Parameters such as `charset` are ignored.
+    pub fn allows(&self, content_type: &str) -> bool {
+        let content_type = essence(content_type);
+        let top_level = content_type.split('/').next().unwrap_or_default();
+
+        self.0.iter().any(|pattern| {
+            pattern == "*/*"
+                || *pattern == content_type
+                || pattern
+                    .strip_suffix("/*")
+                    .is_some_and(|prefix| prefix == top_level)
+        })
+    }
+}
+
+impl FormFile {
+    /// Indicates whether the file's declared content type is allowed by
+    /// `list`.
+    pub fn is_allowed(&self, list: &MimeAllowList) -> bool {
+        list.allows(&self.content_type)
+    }
+}
@@ -0,0 +1,357 @@
+use crate::FormFile;
+use futures::future::BoxFuture;
+use nebula_status::{Status, StatusCode};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeaderMap;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    #[cfg(unix)]
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    const EICAR: &[u8] = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+    /// Answers a single INSTREAM request the way clamd does, reporting any
+    /// stream that contains the EICAR test string as infected.
+    fn fake_clamd(mut conn: impl Read + Write) {
+        let mut command = [0u8; 10];
+        conn.read_exact(&mut command).unwrap();
+        assert_eq!(&command, b"zINSTREAM\0");
+
+        let mut data = Vec::new();
+        loop {
+            let mut len = [0u8; 4];
+            conn.read_exact(&mut len).unwrap();
+            let len = u32::from_be_bytes(len) as usize;
+            if len == 0 {
+                break;
+            }
+            let mut chunk = vec![0u8; len];
+            conn.read_exact(&mut chunk).unwrap();
+            data.extend_from_slice(&chunk);
+        }
+
+        let infected = data.windows(EICAR.len()).any(|win| win == EICAR);
+        let reply: &[u8] = if infected {
+            b"stream: Eicar-Test-Signature FOUND\0"
+        } else {
+            b"stream: OK\0"
+        };
+        conn.write_all(reply).unwrap();
+    }
+
+    fn fake_clamd_tcp(requests: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for conn in listener.incoming().take(requests) {
+                fake_clamd(conn.unwrap());
+            }
+        });
+        addr
+    }
+
+    fn get_file(content: &[u8]) -> FormFile {
+        FormFile {
+            filename: String::from("upload.txt"),
+            content_type: String::from("text/plain"),
+            bytes: content.to_vec().into(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn clamd_tcp_clean_and_infected() {
+        let addr = fake_clamd_tcp(2);
+        // A small chunk size makes sure the file is split across chunks
+        let scanner = ClamdScanner::tcp(addr).chunk_size(7);
+
+        let verdict = scanner.scan(&get_file(b"harmless")).await.unwrap();
+        assert_eq!(verdict, Verdict::Clean);
+
+        let mut content = b"prefix ".to_vec();
+        content.extend_from_slice(EICAR);
+        let verdict = scanner.scan(&get_file(&content)).await.unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Infected(String::from("Eicar-Test-Signature"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn clamd_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clamd.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || fake_clamd(listener.incoming().next().unwrap().unwrap()));
+
+        let verdict = ClamdScanner::unix(&path)
+            .scan(&get_file(EICAR))
+            .await
+            .unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Infected(String::from("Eicar-Test-Signature"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn clamd_unreachable_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = ClamdScanner::unix(dir.path().join("missing.sock"))
+            .scan(&get_file(b"harmless"))
+            .await;
+        match result {
+            Err(Error::Io(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_clamd_replies() {
+        assert_eq!(parse_reply(b"stream: OK\0").unwrap(), Verdict::Clean);
+        assert_eq!(
+            parse_reply(b"stream: Win.Test.EICAR_HDB-1 FOUND\n").unwrap(),
+            Verdict::Infected(String::from("Win.Test.EICAR_HDB-1"))
+        );
+        match parse_reply(b"INSTREAM size limit exceeded. ERROR\0") {
+            Err(Error::Protocol(msg)) => assert!(msg.contains("size limit")),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn infected_status_is_client_error() {
+        let status = Status::from(ScanRejection::Infected {
+            filename: String::from("evil.exe"),
+            signature: String::from("Eicar-Test-Signature"),
+        });
+        assert_eq!(status.code(), &StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(status.message().unwrap().contains("evil.exe"));
+    }
+}
+
+/// The default size of each chunk sent to clamd.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// The default time allowed for connecting to clamd and scanning one file.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Errors that can occur while scanning a file.
+#[derive(Debug)]
+pub enum Error {
+    /// The scanner could not be reached or the connection failed.
+    Io(io::Error),
+    /// The scanner did not respond in time.
+    Timeout,
+    /// The scanner returned an error or a response that was not understood.
+    Protocol(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "Could not communicate with scanner: {}", err),
+            Error::Timeout => write!(f, "Scanner timed out"),
+            Error::Protocol(msg) => write!(f, "Scanner error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// The result of scanning a single file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Nothing was found.
+    Clean,
+    /// The file matched the named signature.
+    Infected(String),
+}
+
+/// A hook that inspects every file uploaded through `scanning_form_filter`
+/// before the `Form` reaches a handler.
+///
+/// Requires `features = "server-warp"`.
+pub trait Scanner: Send + Sync {
+    /// Scans a single file.
+    fn scan<'a>(&'a self, file: &'a FormFile) -> BoxFuture<'a, Result<Verdict, Error>>;
+}
+
+/// The reasons a file can be rejected while a form is scanned.
+#[derive(Debug)]
+pub(crate) enum ScanRejection {
+    Infected { filename: String, signature: String },
+    Failed(Error),
+}
+
+impl From<ScanRejection> for Status<String> {
+    fn from(rejection: ScanRejection) -> Self {
+        match rejection {
+            ScanRejection::Infected {
+                filename,
+                signature,
+            } => Status::with_message(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("file {} was rejected: {}", filename, signature),
+            ),
+            ScanRejection::Failed(err) => {
+                Status::with_message(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            }
+        }
+    }
+}
+
+/// Scans `file` and returns an error if it is infected or cannot be
+/// scanned.
+pub(crate) async fn check(scanner: &dyn Scanner, file: &FormFile) -> Result<(), ScanRejection> {
+    match scanner.scan(file).await.map_err(ScanRejection::Failed)? {
+        Verdict::Clean => Ok(()),
+        Verdict::Infected(signature) => Err(ScanRejection::Infected {
+            filename: file.filename.clone(),
+            signature,
+        }),
+    }
+}
+
+/// Where a clamd daemon is listening.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClamdAddr {
+    /// A TCP address, as set by clamd's `TCPAddr` and `TCPSocket` options.
+    Tcp(SocketAddr),
+    /// A Unix socket, as set by clamd's `LocalSocket` option. Only available
+    /// on Unix platforms.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// A `Scanner` that sends files to a clamd daemon using the `INSTREAM`
+/// command.
+///
+/// Note that clamd rejects streams larger than its `StreamMaxLength`
+/// setting. Usually it closes the connection while the file is still being
+/// sent, which results in `Error::Io` (a broken pipe or reset connection);
+/// if the whole file was sent before that, its reply results in
+/// `Error::Protocol` instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClamdScanner {
+    addr: ClamdAddr,
+    chunk_size: usize,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    /// Creates a scanner that connects to clamd at the given address.
+    pub fn new(addr: ClamdAddr) -> Self {
+        ClamdScanner {
+            addr,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Creates a scanner that connects to clamd over TCP.
+    pub fn tcp(addr: SocketAddr) -> Self {
+        Self::new(ClamdAddr::Tcp(addr))
+    }
+
+    /// Creates a scanner that connects to clamd over a Unix socket. Only
+    /// available on Unix platforms.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Self::new(ClamdAddr::Unix(path.into()))
+    }
+
+    /// Sets the size of the chunks the file is sent in. Defaults to
+    /// `DEFAULT_CHUNK_SIZE`.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Sets the time allowed for connecting and scanning one file. Defaults
+    /// to `DEFAULT_TIMEOUT`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn scan_with_connection(&self, file: &FormFile) -> Result<Verdict, Error> {
+        match &self.addr {
+            ClamdAddr::Tcp(addr) => {
+                instream(TcpStream::connect(addr).await?, file, self.chunk_size).await
+            }
+            #[cfg(unix)]
+            ClamdAddr::Unix(path) => {
+                instream(UnixStream::connect(path).await?, file, self.chunk_size).await
+            }
+        }
+    }
+}
+
+impl Scanner for ClamdScanner {
+    fn scan<'a>(&'a self, file: &'a FormFile) -> BoxFuture<'a, Result<Verdict, Error>> {
+        Box::pin(async move {
+            tokio::time::timeout(self.timeout, self.scan_with_connection(file))
+                .await
+                .map_err(|_| Error::Timeout)?
+        })
+    }
+}
+
+/// Sends `file` over `stream` using the clamd `INSTREAM` command and reads
+/// the verdict.
+async fn instream<S>(mut stream: S, file: &FormFile, chunk_size: usize) -> Result<Verdict, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in file.bytes.chunks(chunk_size) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&[0, 0, 0, 0]).await?;
+    stream.flush().await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    parse_reply(&reply)
+}
+
+/// Parses a clamd reply such as `stream: OK` or
+/// `stream: Eicar-Test-Signature FOUND`.
+fn parse_reply(reply: &[u8]) -> Result<Verdict, Error> {
+    let reply = String::from_utf8_lossy(reply);
+    let reply = reply.trim_end_matches(&['\0', '\n'][..]);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Infected(signature.to_string()))
+    } else {
+        Err(Error::Protocol(result.to_string()))
+    }
+}
@@ -0,0 +1,648 @@
+use crate::{Field, Form, FormFile, HeaderMap, MergeStrategy};
+use hmac::{Hmac, Mac, NewMac};
+use http::header::{HeaderName, HeaderValue};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "server-warp")]
+use nebula_status::{Status, StatusCode};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    const KEY: &[u8] = b"super secret signing key";
+
+    fn get_steps() -> Vec<String> {
+        vec![String::from("contact"), String::from("documents")]
+    }
+
+    fn get_contact() -> Form {
+        let mut form = Form::new();
+        form.insert("name", Field::Text(String::from("Jane Doe")));
+        form.insert("email", Field::Text(String::from("jane@example.com")));
+        form
+    }
+
+    fn get_documents() -> Form {
+        let mut form = Form::new();
+        form.insert(
+            "resume",
+            Field::File(FormFile {
+                filename: String::from("resume.pdf"),
+                content_type: String::from("application/pdf"),
+                bytes: Bytes::from_static(b"%PDF-1.4\x00\xff"),
+                headers: {
+                    let mut headers = HeaderMap::new();
+                    headers.insert("content-id", HeaderValue::from_static("<resume>"));
+                    headers.insert(
+                        "x-original-name",
+                        HeaderValue::from_bytes(b"r\xe9sum\xe9.pdf").unwrap(),
+                    );
+                    headers
+                },
+            }),
+        );
+        form
+    }
+
+    fn run_steps<S: SessionStore>(manager: SessionManager<S>) {
+        let id = manager.start().unwrap();
+
+        let progress = manager.submit(&id, "contact", get_contact()).unwrap();
+        assert_eq!(
+            progress,
+            Progress::Incomplete {
+                remaining: vec![String::from("documents")]
+            }
+        );
+
+        let progress = manager.submit(&id, "documents", get_documents()).unwrap();
+        let mut expected = get_contact();
+        expected.extend(get_documents().into_iter());
+        assert_eq!(progress, Progress::Complete(expected));
+
+        match manager.submit(&id, "contact", get_contact()) {
+            Err(Error::NotFound) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn memory_store_completes_all_steps() {
+        run_steps(SessionManager::new(MemoryStore::new(), KEY, get_steps()));
+    }
+
+    #[test]
+    fn file_store_completes_all_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        run_steps(SessionManager::new(store, KEY, get_steps()));
+    }
+
+    #[test]
+    fn session_round_trips_through_bytes() {
+        let mut session = Session::new(SystemTime::now());
+        session.form = get_documents();
+        session.form.extend(get_contact().into_iter());
+        session.completed.push(String::from("contact"));
+
+        let parsed = Session::from_bytes(&session.to_bytes()).unwrap();
+        assert_eq!(parsed.form, session.form);
+        assert_eq!(parsed.completed, session.completed);
+    }
+
+    #[test]
+    fn tampered_id_is_rejected() {
+        let manager = SessionManager::new(MemoryStore::new(), KEY, get_steps());
+        let id = manager.start().unwrap();
+        let other = SessionManager::new(MemoryStore::new(), b"other key", get_steps());
+        let forged = other.start().unwrap();
+
+        let (raw, _) = id.split_at(id.find('.').unwrap());
+        let (_, sig) = forged.split_at(forged.find('.').unwrap());
+        let tampered = format!("{}{}", raw, sig);
+
+        for bad in &[tampered.as_str(), "not-an-id", "../../etc/passwd.00"] {
+            match manager.submit(bad, "contact", get_contact()) {
+                Err(Error::InvalidSignature) => {}
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_step_is_rejected() {
+        let manager = SessionManager::new(MemoryStore::new(), KEY, get_steps());
+        let id = manager.start().unwrap();
+        match manager.submit(&id, "payment", get_contact()) {
+            Err(Error::UnknownStep(step)) => assert_eq!(step, "payment"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn expired_session_is_rejected_and_purged() {
+        let store = MemoryStore::new();
+        let manager = SessionManager::new(store, KEY, get_steps()).with_ttl(Duration::from_secs(0));
+        let id = manager.start().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        match manager.submit(&id, "contact", get_contact()) {
+            Err(Error::Expired) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match manager.submit(&id, "contact", get_contact()) {
+            Err(Error::NotFound) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn file_store_purges_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        let past = SystemTime::now() - Duration::from_secs(60);
+        store.save("aa", &Session::new(past)).unwrap();
+        store
+            .save(
+                "bb",
+                &Session::new(SystemTime::now() + Duration::from_secs(60)),
+            )
+            .unwrap();
+
+        assert_eq!(store.purge_expired(SystemTime::now()).unwrap(), 1);
+        assert!(store.load("aa").unwrap().is_none());
+        assert!(store.load("bb").unwrap().is_some());
+    }
+}
+
+/// The default length of time a session stays valid after its last step.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Errors that can occur while working with multi-step form sessions.
+#[derive(Debug)]
+pub enum Error {
+    /// The session id was malformed or its signature did not match.
+    InvalidSignature,
+    /// No session exists with the given id.
+    NotFound,
+    /// The session existed but has expired.
+    Expired,
+    /// The submitted step is not one of the configured steps.
+    UnknownStep(String),
+    /// Stored session data could not be decoded.
+    Corrupt(String),
+    /// The session store could not be read or written.
+    Io(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidSignature => write!(f, "Invalid session id"),
+            Error::NotFound => write!(f, "Session not found"),
+            Error::Expired => write!(f, "Session has expired"),
+            Error::UnknownStep(step) => write!(f, "Unknown form step: {}", step),
+            Error::Corrupt(msg) => write!(f, "Could not decode session data: {}", msg),
+            Error::Io(err) => write!(f, "Could not access session store: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "server-warp")]
+impl From<Error> for Status<String> {
+    fn from(err: Error) -> Self {
+        let code = match err {
+            Error::InvalidSignature | Error::UnknownStep(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::Expired => StatusCode::GONE,
+            Error::Corrupt(_) | Error::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Status::with_message(code, err.to_string())
+    }
+}
+
+/// The server-side state of a multi-step form.
+///
+/// Requires `features = "sessions"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    expires: SystemTime,
+    completed: Vec<String>,
+    form: Form,
+}
+
+impl Session {
+    fn new(expires: SystemTime) -> Self {
+        Session {
+            expires,
+            completed: Vec::new(),
+            form: Form::new(),
+        }
+    }
+
+    /// Returns the time after which this session is no longer valid.
+    pub fn expires(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// Indicates whether the session has expired as of `now`.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires <= now
+    }
+
+    /// Returns the names of the steps submitted so far, in order.
+    pub fn completed(&self) -> &[String] {
+        &self.completed
+    }
+
+    /// Returns the fields accumulated so far.
+    pub fn form(&self) -> &Form {
+        &self.form
+    }
+
+    /// Encodes the session as JSON, with file contents and part header values
+    /// encoded as base64.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let expires = self
+            .expires
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let fields: Map<String, Value> = self
+            .form
+            .iter()
+            .map(|(name, field)| {
+                let value = match field {
+                    Field::Text(txt) => serde_json::json!({ "text": txt }),
+                    Field::File(file) => serde_json::json!({
+                        "file": {
+                            "filename": file.filename,
+                            "content_type": file.content_type,
+                            "content": base64::encode(&file.bytes),
+                            "headers": file
+                                .headers
+                                .iter()
+                                .map(|(name, value)| {
+                                    (name.as_str(), base64::encode(value.as_bytes()))
+                                })
+                                .collect::<Vec<_>>(),
+                        }
+                    }),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+
+        let value = serde_json::json!({
+            "expires": expires,
+            "completed": self.completed,
+            "fields": fields,
+        });
+        value.to_string().into_bytes()
+    }
+
+    /// Decodes a session previously encoded with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        fn corrupt(what: &str) -> Error {
+            Error::Corrupt(format!("missing or invalid {}", what))
+        }
+
+        fn get_str<'a>(value: &'a Value, key: &str) -> Result<&'a str, Error> {
+            value[key].as_str().ok_or_else(|| corrupt(key))
+        }
+
+        // Sessions encoded before headers were kept have no "headers" key.
+        fn get_headers(file: &Value) -> Result<HeaderMap, Error> {
+            let mut headers = HeaderMap::new();
+            let pairs = match file.get("headers") {
+                Some(pairs) => pairs.as_array().ok_or_else(|| corrupt("headers"))?,
+                None => return Ok(headers),
+            };
+            for pair in pairs {
+                let (name, value) = match pair.as_array().map(Vec::as_slice) {
+                    Some([name, value]) => (name.as_str(), value.as_str()),
+                    _ => return Err(corrupt("header")),
+                };
+                let name = name
+                    .and_then(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+                    .ok_or_else(|| corrupt("header name"))?;
+                let value = value
+                    .and_then(|value| base64::decode(value).ok())
+                    .and_then(|value| HeaderValue::from_bytes(&value).ok())
+                    .ok_or_else(|| corrupt("header value"))?;
+                headers.append(name, value);
+            }
+            Ok(headers)
+        }
+
+        let value: Value =
+            serde_json::from_slice(bytes).map_err(|e| Error::Corrupt(e.to_string()))?;
+
+        let expires = value["expires"]
+            .as_u64()
+            .ok_or_else(|| corrupt("expires"))?;
+        let expires = UNIX_EPOCH + Duration::from_secs(expires);
+
+        let completed = value["completed"]
+            .as_array()
+            .ok_or_else(|| corrupt("completed"))?
+            .iter()
+            .map(|step| {
+                step.as_str()
+                    .map(String::from)
+                    .ok_or_else(|| corrupt("step"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut form = Form::new();
+        for (name, field) in value["fields"]
+            .as_object()
+            .ok_or_else(|| corrupt("fields"))?
+        {
+            let field = match (field.get("text"), field.get("file")) {
+                (Some(txt), None) => {
+                    Field::Text(txt.as_str().ok_or_else(|| corrupt("text"))?.to_string())
+                }
+                (None, Some(file)) => Field::File(FormFile {
+                    filename: get_str(file, "filename")?.to_string(),
+                    content_type: get_str(file, "content_type")?.to_string(),
+                    bytes: base64::decode(get_str(file, "content")?)
+                        .map_err(|e| Error::Corrupt(e.to_string()))?
+                        .into(),
+                    headers: get_headers(file)?,
+                }),
+                _ => return Err(corrupt("field")),
+            };
+            form.insert(name, field);
+        }
+
+        Ok(Session {
+            expires,
+            completed,
+            form,
+        })
+    }
+}
+
+/// A backend that persists `Session`s by their (unsigned) id.
+///
+/// Ids passed to a store have already had their signature verified and only
+/// contain lowercase hexadecimal characters.
+///
+/// Requires `features = "sessions"`.
+pub trait SessionStore: Send + Sync {
+    /// Returns the session with the given id, if it exists.
+    fn load(&self, id: &str) -> Result<Option<Session>, Error>;
+    /// Creates or replaces the session with the given id.
+    fn save(&self, id: &str, session: &Session) -> Result<(), Error>;
+    /// Removes the session with the given id, if it exists.
+    fn remove(&self, id: &str) -> Result<(), Error>;
+    /// Removes every session that has expired as of `now` and returns how
+    /// many were removed.
+    fn purge_expired(&self, now: SystemTime) -> Result<usize, Error>;
+}
+
+/// A `SessionStore` that keeps sessions in memory. Sessions are lost when
+/// the process exits.
+///
+/// Requires `features = "sessions"`.
+#[derive(Debug, Default)]
+pub struct MemoryStore(Mutex<HashMap<String, Session>>);
+
+impl MemoryStore {
+    /// Creates a new, empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        // A panic while holding the lock cannot leave the map in an invalid
+        // state, so a poisoned lock is still safe to use.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Result<Option<Session>, Error> {
+        Ok(self.sessions().get(id).cloned())
+    }
+
+    fn save(&self, id: &str, session: &Session) -> Result<(), Error> {
+        self.sessions().insert(id.to_string(), session.clone());
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), Error> {
+        self.sessions().remove(id);
+        Ok(())
+    }
+
+    fn purge_expired(&self, now: SystemTime) -> Result<usize, Error> {
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.is_expired(now));
+        Ok(before - sessions.len())
+    }
+}
+
+/// A `SessionStore` that keeps each session in its own file inside a
+/// directory.
+///
+/// Requires `features = "sessions"`.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Creates a store that keeps sessions in `dir`, creating the directory
+    /// if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileStore { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.session", id))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> Result<Option<Session>, Error> {
+        match fs::read(self.path(id)) {
+            Ok(bytes) => Session::from_bytes(&bytes).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, id: &str, session: &Session) -> Result<(), Error> {
+        // Write to a temporary file first so a crash never leaves a
+        // partially-written session behind.
+        let tmp = self.dir.join(format!("{}.tmp", id));
+        fs::write(&tmp, session.to_bytes())?;
+        fs::rename(&tmp, self.path(id))?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(id)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn purge_expired(&self, now: SystemTime) -> Result<usize, Error> {
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("session") {
+                continue;
+            }
+
+            let expired = match Session::from_bytes(&fs::read(&path)?) {
+                Ok(session) => session.is_expired(now),
+                // Sessions that cannot be decoded can never be used again.
+                Err(_) => true,
+            };
+
+            if expired {
+                fs::remove_file(&path)?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+}
+
+/// The state of a multi-step form after a step is submitted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress {
+    /// Some steps have not been submitted yet.
+    Incomplete {
+        /// The steps that remain, in their configured order.
+        remaining: Vec<String>,
+    },
+    /// Every step has been submitted. Contains the fields of all steps
+    /// combined; the session has been removed from the store.
+    Complete(Form),
+}
+
+/// Accumulates the `Form`s submitted for each step of a multi-step form
+/// under a signed session id.
+///
+/// Session ids are random and signed with HMAC-SHA256, so clients cannot
+/// guess or forge the id of another session. If a step is submitted more
+/// than once, its newer fields replace older fields with the same name, but
+/// fields left out of the newer submission are kept.
+///
+/// Requires `features = "sessions"`.
+pub struct SessionManager<S: SessionStore> {
+    store: S,
+    key: Vec<u8>,
+    steps: Vec<String>,
+    ttl: Duration,
+}
+
+impl<S: SessionStore> SessionManager<S> {
+    /// Creates a manager that signs ids with `key` and considers a session
+    /// complete once every step in `steps` has been submitted.
+    pub fn new(store: S, key: &[u8], steps: Vec<String>) -> Self {
+        SessionManager {
+            store,
+            key: key.to_vec(),
+            steps,
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Sets how long a session stays valid after its last step. Defaults to
+    /// `DEFAULT_TTL`.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns the underlying store, e.g. to call `purge_expired`.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_varkey(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    fn sign(&self, id: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(id.as_bytes());
+        format!("{}.{}", id, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Checks the signature of a signed id and returns the unsigned id.
+    fn verify<'a>(&self, signed: &'a str) -> Result<&'a str, Error> {
+        let mut parts = signed.splitn(2, '.');
+        let id = parts.next().unwrap_or_default();
+        let sig = parts.next().ok_or(Error::InvalidSignature)?;
+        let sig = hex::decode(sig).map_err(|_| Error::InvalidSignature)?;
+
+        if id.is_empty() || !id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return Err(Error::InvalidSignature);
+        }
+
+        let mut mac = self.mac();
+        mac.update(id.as_bytes());
+        mac.verify(&sig).map_err(|_| Error::InvalidSignature)?;
+        Ok(id)
+    }
+
+    /// Starts a new session and returns its signed id, which should be sent
+    /// to the client (e.g. in a hidden field or cookie) and returned with
+    /// each step.
+    pub fn start(&self) -> Result<String, Error> {
+        let id = hex::encode(rand::random::<[u8; 16]>());
+        self.store
+            .save(&id, &Session::new(SystemTime::now() + self.ttl))?;
+        Ok(self.sign(&id))
+    }
+
+    /// Adds the fields submitted for `step` to the session with the given
+    /// signed id.
+    ///
+    /// Once every step has been submitted, the session is removed and the
+    /// combined `Form` is returned.
+    pub fn submit(&self, signed_id: &str, step: &str, form: Form) -> Result<Progress, Error> {
+        let id = self.verify(signed_id)?;
+
+        if !self.steps.iter().any(|s| s == step) {
+            return Err(Error::UnknownStep(step.to_string()));
+        }
+
+        let now = SystemTime::now();
+        let mut session = self.store.load(id)?.ok_or(Error::NotFound)?;
+        if session.is_expired(now) {
+            self.store.remove(id)?;
+            return Err(Error::Expired);
+        }
+
+        session.form.merge(form, &MergeStrategy::Overwrite);
+        if !session.completed.iter().any(|s| s == step) {
+            session.completed.push(step.to_string());
+        }
+
+        let remaining: Vec<String> = self
+            .steps
+            .iter()
+            .filter(|s| !session.completed.contains(s))
+            .cloned()
+            .collect();
+
+        if remaining.is_empty() {
+            self.store.remove(id)?;
+            return Ok(Progress::Complete(session.form));
+        }
+
+        session.expires = now + self.ttl;
+        self.store.save(id, &session)?;
+        Ok(Progress::Incomplete { remaining })
+    }
+}
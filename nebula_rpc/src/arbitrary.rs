@@ -0,0 +1,28 @@
+use crate::config::{Config, Value};
+use proptest::collection::hash_map;
+use proptest::prelude::*;
+
+/// Returns a strategy for configuration keys, including empty keys and keys
+/// containing the `.` used by `ConfigExt::get_path`.
+pub fn config_key() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z_][a-z0-9_]{0,11}", "\\PC{0,12}"]
+}
+
+/// Returns a strategy for configuration values nested up to `depth` levels
+/// deep.
+pub fn config_value(depth: u32) -> impl Strategy<Value = Value> {
+    "\\PC{0,32}"
+        .prop_map(Value::Leaf)
+        .prop_recursive(depth, 64, 4, |inner| {
+            hash_map(config_key(), inner, 0..4).prop_map(Value::Node)
+        })
+}
+
+/// Returns a strategy for configurations nested up to 8 levels deep.
+///
+/// `Config` is a `HashMap`, so it cannot implement `Arbitrary` itself.
+///
+/// Requires `features = "proptest"`.
+pub fn config() -> impl Strategy<Value = Config> {
+    hash_map(config_key(), config_value(8), 0..6)
+}
@@ -3,6 +3,7 @@ use crate::rpc;
 use bytes::Bytes;
 use http::header::{HeaderName, HeaderValue, InvalidHeaderName, InvalidHeaderValue, ToStrError};
 use http::status::InvalidStatusCode;
+use http::HeaderMap;
 use nebula_form::{Field, Form, FormFile};
 use nebula_status::{Status, StatusCode, StatusData};
 use std::collections::HashMap;
@@ -12,17 +13,22 @@ use std::fmt;
 mod tests {
     use super::*;
     use crate::rpc;
+    use nebula_status::correlation;
 
     // These functions should be kept in sync to ensure that the normal version
     // and the RPC type represent the same information. As long as these stay
     // in sync, all tests and functions will be correct.
 
     fn get_form_file() -> FormFile {
-        FormFile {
-            filename: "some form file.txt".to_string(),
-            content_type: "text/plain".to_string(),
-            bytes: b"text content\nstuff".to_vec().into(),
-        }
+        let mut headers = HeaderMap::new();
+        headers.append("x-checksum", HeaderValue::from_static("sha256=abc"));
+        headers.append("x-checksum", HeaderValue::from_static("md5=def"));
+        FormFile::new(
+            "some form file.txt".to_string(),
+            "text/plain".to_string(),
+            b"text content\nstuff".to_vec().into(),
+        )
+        .with_headers(headers)
     }
 
     fn get_rpc_form_file() -> rpc::File {
@@ -30,6 +36,16 @@ mod tests {
             name: "some form file.txt".to_string(),
             content_type: "text/plain".to_string(),
             content: b"text content\nstuff".to_vec(),
+            headers: {
+                let mut headers = HashMap::new();
+                headers.insert(
+                    "x-checksum".to_string(),
+                    rpc::PartHeaders {
+                        headers: vec![b"sha256=abc".to_vec(), b"md5=def".to_vec()],
+                    },
+                );
+                headers
+            },
         }
     }
 
@@ -131,6 +147,7 @@ mod tests {
             code: 418u32,
             headers,
             body: b"{ success: true }".to_vec(),
+            correlation_id: String::new(),
         }
     }
 
@@ -143,6 +160,31 @@ mod tests {
         assert_eq!(file, expected);
     }
 
+    #[test]
+    fn non_ascii_part_headers_round_trip() {
+        let body = b"--b\r\n\
+Content-Disposition: form-data; name=\"cv\"; filename=\"cv.pdf\"\r\n\
+Content-Type: application/pdf\r\n\
+X-Original-Name: r\xc3\xa9sum\xc3\xa9.pdf\r\n\
+X-Latin-Name: r\xe9sum\xe9.pdf\r\n\r\n\
+%PDF\r\n--b--\r\n";
+        let form = Form::from_multipart_bytes(body, b"b").expect("form should parse");
+        let headers = &form.get("cv").and_then(Field::as_file).unwrap().headers;
+        assert_eq!(headers["x-latin-name"].as_bytes(), b"r\xe9sum\xe9.pdf");
+        let rpc_form = form.clone().into_rpc().expect("conversion should not fail");
+        assert_eq!(Form::from_rpc(rpc_form).unwrap(), form);
+
+        let mut rpc_file = get_rpc_form_file();
+        rpc_file.headers.insert(
+            "x-original-name".to_string(),
+            rpc::PartHeaders {
+                headers: vec!["é".as_bytes().to_vec()],
+            },
+        );
+        let file = FormFile::from_rpc(rpc_file.clone()).expect("conversion should not fail");
+        assert_eq!(file.into_rpc().unwrap(), rpc_file);
+    }
+
     #[test]
     fn form_file_into_rpc() {
         let file = get_form_file();
@@ -200,6 +242,49 @@ mod tests {
         assert_eq!(rpc_form, expected);
     }
 
+    #[cfg(feature = "proptest")]
+    mod round_trip {
+        use super::*;
+        use crate::arbitrary::config;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn form(form in any::<Form>()) {
+                let rpc_form = form.clone().into_rpc().unwrap();
+                prop_assert_eq!(Form::from_rpc(rpc_form).unwrap(), form);
+            }
+
+            #[test]
+            fn status(status in any::<Status<Bytes>>()) {
+                let rpc_status = status.clone().into_rpc().unwrap();
+                prop_assert_eq!(Status::<Bytes>::from_rpc(rpc_status).unwrap(), status);
+            }
+
+            #[test]
+            fn config_and_form(config in config(), form in any::<Form>()) {
+                let request = (config.clone(), form.clone()).into_rpc().unwrap();
+                let (rt_config, rt_form) = <(Config, Form)>::from_rpc(request).unwrap();
+                prop_assert_eq!(rt_config, config);
+                prop_assert_eq!(rt_form, form);
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_form_round_trip() {
+        let keys = nebula_form::Keyring::new("test", &[1; 32]).unwrap();
+        let mut form = get_form();
+        form.encrypt_fields(&["some_file", "email"], &keys).unwrap();
+
+        let rpc_form = form.clone().into_rpc().expect("conversion should not fail");
+        let mut converted = Form::from_rpc(rpc_form).expect("conversion should not fail");
+        assert_eq!(converted, form);
+
+        converted.decrypt_fields(&keys).unwrap();
+        assert_eq!(converted, get_form());
+    }
+
     #[test]
     fn config_from_rpc() {
         let rpc_config = get_rpc_config();
@@ -224,6 +309,59 @@ mod tests {
         assert_eq!(status, expected);
     }
 
+    #[test]
+    fn status_with_repeated_headers_from_rpc() {
+        let mut rpc_status = get_rpc_status();
+        rpc_status.headers.insert(
+            "set-cookie".to_string(),
+            rpc::Headers {
+                headers: vec!["a=1".to_string(), "b=2".to_string()],
+            },
+        );
+        let status = Status::<Bytes>::from_rpc(rpc_status).expect("conversion should not fail");
+        let cookies: Vec<_> = status.headers().get_all("set-cookie").iter().collect();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+    }
+
+    #[test]
+    fn status_correlation_id_round_trips() {
+        let status = Status::new(StatusCode::INTERNAL_SERVER_ERROR).with_correlation_id("req-42");
+        let rpc_status = status
+            .clone()
+            .into_rpc()
+            .expect("conversion should not fail");
+        assert_eq!(rpc_status.correlation_id, "req-42");
+
+        let back = Status::<Bytes>::from_rpc(rpc_status).expect("conversion should not fail");
+        assert_eq!(back.correlation_id(), Some("req-42"));
+        assert_eq!(back, status.into_bytes());
+    }
+
+    #[test]
+    fn proxy_correlation_header_round_trips() {
+        let mut status = Status::new(StatusCode::NOT_FOUND);
+        status
+            .headers_mut()
+            .insert(correlation::HEADER, HeaderValue::from_static("proxy-7"));
+        let rpc_status = status
+            .clone()
+            .into_rpc()
+            .expect("conversion should not fail");
+        assert_eq!(rpc_status.correlation_id, "");
+
+        let back = Status::<Bytes>::from_rpc(rpc_status).expect("conversion should not fail");
+        assert_eq!(back.headers()[correlation::HEADER], "proxy-7");
+        assert_eq!(back, status.into_bytes());
+    }
+
+    #[test]
+    fn status_without_correlation_id_from_rpc() {
+        let mut rpc_status = get_rpc_status();
+        rpc_status.code = 500;
+        let status = Status::<Bytes>::from_rpc(rpc_status).expect("conversion should not fail");
+        assert_eq!(status.correlation_id(), None);
+    }
+
     #[test]
     fn status_into_rpc() {
         let status = get_status();
@@ -288,6 +426,57 @@ impl From<InvalidHeaderValue> for Error {
     }
 }
 
+fn headers_from_rpc(other: HashMap<String, rpc::Headers>) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    for (key, list) in other.into_iter() {
+        for val in list.headers.into_iter() {
+            let key = key.as_str().parse::<HeaderName>()?;
+            let val = val.as_str().parse::<HeaderValue>()?;
+
+            headers.append(key, val);
+        }
+    }
+    Ok(headers)
+}
+
+fn part_headers_from_rpc(other: HashMap<String, rpc::PartHeaders>) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    for (key, list) in other.into_iter() {
+        for val in list.headers.into_iter() {
+            let key = key.as_str().parse::<HeaderName>()?;
+            let val = HeaderValue::from_bytes(&val)?;
+
+            headers.append(key, val);
+        }
+    }
+    Ok(headers)
+}
+
+fn part_headers_into_rpc(headers: &HeaderMap) -> HashMap<String, rpc::PartHeaders> {
+    let mut rpc_headers: HashMap<String, rpc::PartHeaders> = HashMap::new();
+    for (key, val) in headers.iter() {
+        rpc_headers
+            .entry(key.to_string())
+            .or_default()
+            .headers
+            .push(val.as_bytes().to_vec());
+    }
+    rpc_headers
+}
+
+fn headers_into_rpc(headers: &HeaderMap) -> Result<HashMap<String, rpc::Headers>, Error> {
+    let mut rpc_headers: HashMap<String, rpc::Headers> = HashMap::new();
+    for (key, val) in headers.iter() {
+        let val = val.to_str().map_err(Error::HeaderValueToStr)?.to_string();
+        rpc_headers
+            .entry(key.to_string())
+            .or_default()
+            .headers
+            .push(val);
+    }
+    Ok(rpc_headers)
+}
+
 pub trait IntoRPC {
     type RPCType: prost::Message;
     fn into_rpc(self) -> Result<Self::RPCType, Error>;
@@ -301,11 +490,8 @@ pub trait FromRPC: Sized {
 impl FromRPC for FormFile {
     type RPCType = rpc::File;
     fn from_rpc(other: Self::RPCType) -> Result<Self, Error> {
-        let file = FormFile {
-            filename: other.name,
-            content_type: other.content_type,
-            bytes: other.content.into(),
-        };
+        let file = FormFile::new(other.name, other.content_type, other.content.into())
+            .with_headers(part_headers_from_rpc(other.headers)?);
         Ok(file)
     }
 }
@@ -317,6 +503,7 @@ impl IntoRPC for FormFile {
             name: self.filename,
             content_type: self.content_type,
             content: self.bytes.into_iter().collect(),
+            headers: part_headers_into_rpc(&self.headers),
         };
         Ok(file)
     }
@@ -378,17 +565,17 @@ impl IntoRPC for Form {
 impl FromRPC for Status<Bytes> {
     type RPCType = rpc::Status;
     fn from_rpc(other: Self::RPCType) -> Result<Self, Error> {
-        let mut status =
-            Status::with_data(StatusCode::from_u16(other.code as u16)?, other.body.into());
-        for (key, list) in other.headers.into_iter() {
-            for val in list.headers.into_iter() {
-                let key = key.as_str().parse::<HeaderName>()?;
-                let val = val.as_str().parse::<HeaderValue>()?;
-
-                status.headers_mut().insert(key, val);
-            }
-        }
-        Ok(status)
+        let mut response = http::Response::new(Bytes::from(other.body));
+        *response.status_mut() = StatusCode::from_u16(other.code as u16)?;
+        *response.headers_mut() = headers_from_rpc(other.headers)?;
+
+        // The correlation ID travels on its own, so a header with the same
+        // name, e.g. one set by a proxy, is kept as it was
+        let status = Status::from(response);
+        Ok(match other.correlation_id.as_str() {
+            "" => status,
+            id => status.with_correlation_id(id),
+        })
     }
 }
 
@@ -398,31 +585,11 @@ where
 {
     type RPCType = rpc::Status;
     fn into_rpc(self) -> Result<Self::RPCType, Error> {
-        let headers = {
-            let mut headers = HashMap::new();
-            for (key, val) in self.headers().iter() {
-                let h_list = {
-                    if !headers.contains_key(key) {
-                        headers.insert(key, Vec::new());
-                    }
-                    // Above code should ensure this is always Some()
-                    headers.get_mut(key).unwrap()
-                };
-
-                let val = val.to_str().map_err(Error::HeaderValueToStr)?.to_string();
-                h_list.push(val);
-            }
-
-            headers
-                .into_iter()
-                .map(|(key, val)| (key.to_string(), rpc::Headers { headers: val }))
-                .collect()
-        };
-
         let status = rpc::Status {
             code: self.code().as_u16() as u32,
-            headers,
+            headers: headers_into_rpc(self.headers())?,
             body: self.bytes().to_vec(),
+            correlation_id: self.correlation_id().unwrap_or_default().to_string(),
         };
 
         Ok(status)
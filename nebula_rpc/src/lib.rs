@@ -6,6 +6,8 @@ mod tests {
     }
 }
 
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
 pub mod client;
 pub mod config;
 pub mod convert;
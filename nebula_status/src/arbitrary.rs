@@ -0,0 +1,51 @@
+use crate::{Status, StatusCode};
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use proptest::collection::{hash_map, vec};
+use proptest::prelude::*;
+
+/// Returns a strategy for status codes, covering every class including
+/// codes that have no standard reason phrase.
+pub fn status_code() -> impl Strategy<Value = StatusCode> {
+    (100u16..600).prop_map(|code| StatusCode::from_u16(code).unwrap())
+}
+
+/// Returns a strategy for header names.
+pub fn header_name() -> impl Strategy<Value = HeaderName> {
+    "[a-z][a-z0-9-]{0,15}".prop_map(|name| name.parse().unwrap())
+}
+
+/// Returns a strategy for header values made of visible ASCII and spaces,
+/// without leading or trailing whitespace.
+pub fn header_value() -> impl Strategy<Value = HeaderValue> {
+    "([!-~]([ -~]{0,30}[!-~])?)?".prop_map(|value| value.parse().unwrap())
+}
+
+/// Returns a strategy for header maps, some of which contain several values
+/// for the same name.
+pub fn headers() -> impl Strategy<Value = HeaderMap> {
+    hash_map(header_name(), vec(header_value(), 1..3), 0..6).prop_map(|headers| {
+        let mut map = HeaderMap::new();
+        for (name, values) in headers {
+            for value in values {
+                map.append(name.clone(), value);
+            }
+        }
+        map
+    })
+}
+
+impl Arbitrary for Status<Bytes> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (status_code(), headers(), vec(any::<u8>(), 0..256))
+            .prop_map(|(code, headers, body)| {
+                let mut status = Status::with_data(code, Bytes::from(body));
+                *status.headers_mut() = headers;
+                status
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,641 @@
+use crate::{Empty, Status, StatusCode};
+use bytes::Bytes;
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+use http::Uri;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn header<'a>(status: &'a Status<Bytes>, name: &str) -> Vec<&'a str> {
+        status
+            .headers()
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn builds_status_with_typed_headers() {
+        let status = Status::builder(StatusCode::SERVICE_UNAVAILABLE)
+            .content_type("text/plain; charset=utf-8")
+            .body("down for maintenance")
+            .cache_control("no-store")
+            .retry_after(Duration::from_millis(90_500))
+            .header("x-request-id", "abc123")
+            .build()
+            .unwrap();
+
+        assert_eq!(status.code(), &StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status.bytes(), b"down for maintenance");
+        assert_eq!(
+            header(&status, "content-type"),
+            vec!["text/plain; charset=utf-8"]
+        );
+        assert_eq!(header(&status, "cache-control"), vec!["no-store"]);
+        assert_eq!(header(&status, "retry-after"), vec!["91"]);
+        assert_eq!(header(&status, "x-request-id"), vec!["abc123"]);
+    }
+
+    #[test]
+    fn json_sets_body_and_content_type() {
+        let status = Status::builder(StatusCode::OK)
+            .json(&json!({ "id": 7 }))
+            .build()
+            .unwrap();
+        assert_eq!(status.bytes(), br#"{"id":7}"#);
+        assert_eq!(header(&status, "content-type"), vec!["application/json"]);
+
+        let status = Status::builder(StatusCode::OK)
+            .content_type("application/vnd.example+json")
+            .json(&json!([]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            header(&status, "content-type"),
+            vec!["application/vnd.example+json"]
+        );
+    }
+
+    #[test]
+    fn location_and_challenges() {
+        let status = Status::builder(StatusCode::UNAUTHORIZED)
+            .location("/login?next=%2Faccount")
+            .www_authenticate(
+                "Bearer",
+                &[("realm", "api"), ("error", "invalid \"token\"")],
+            )
+            .www_authenticate("Basic", &[("realm", "api")])
+            .build()
+            .unwrap();
+        assert_eq!(header(&status, "location"), vec!["/login?next=%2Faccount"]);
+        assert_eq!(
+            header(&status, "www-authenticate"),
+            vec![
+                r#"Bearer realm="api", error="invalid \"token\"""#,
+                r#"Basic realm="api""#
+            ]
+        );
+    }
+
+    #[test]
+    fn cookies_are_appended() {
+        let status = Status::builder(StatusCode::NO_CONTENT)
+            .cookie(
+                Cookie::new("session", "abc123")
+                    .path("/")
+                    .max_age(Duration::from_secs(3600))
+                    .secure()
+                    .http_only()
+                    .same_site(SameSite::Lax),
+            )
+            .cookie(Cookie::new("theme", "dark").domain("example.com"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            header(&status, "set-cookie"),
+            vec![
+                "session=abc123; Path=/; Max-Age=3600; Secure; HttpOnly; SameSite=Lax",
+                "theme=dark; Domain=example.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        let build = |builder: StatusBuilder| builder.build().unwrap_err();
+        let builder = || Status::builder(StatusCode::OK);
+
+        assert!(matches!(
+            build(builder().header("bad name", "x")),
+            Error::InvalidHeaderName(_)
+        ));
+        assert!(matches!(
+            build(builder().header("x-ok", "line\nbreak")),
+            Error::InvalidHeaderValue(_)
+        ));
+        assert!(matches!(
+            build(builder().content_type("not a type")),
+            Error::InvalidContentType(_)
+        ));
+        assert!(matches!(
+            build(builder().location("http://exa mple.com")),
+            Error::InvalidLocation(_)
+        ));
+        assert!(matches!(
+            build(builder().cookie(Cookie::new("a;b", "c"))),
+            Error::InvalidCookie(_)
+        ));
+        assert!(matches!(
+            build(builder().cookie(Cookie::new("a", "b c"))),
+            Error::InvalidCookie(_)
+        ));
+        assert!(matches!(
+            build(builder().www_authenticate("Bad Scheme", &[])),
+            Error::InvalidChallenge(_)
+        ));
+    }
+
+    #[test]
+    fn redirects_set_location() {
+        type Constructor = fn(&str) -> Result<Status<Bytes>, Error>;
+        let redirects: [(Constructor, StatusCode); 6] = [
+            (Status::moved_permanently, StatusCode::MOVED_PERMANENTLY),
+            (Status::found, StatusCode::FOUND),
+            (Status::see_other, StatusCode::SEE_OTHER),
+            (Status::temporary_redirect, StatusCode::TEMPORARY_REDIRECT),
+            (Status::permanent_redirect, StatusCode::PERMANENT_REDIRECT),
+            (Status::created, StatusCode::CREATED),
+        ];
+        for (redirect, code) in redirects.iter() {
+            let status = redirect("https://example.com/thanks").unwrap();
+            assert_eq!(status.code(), code);
+            assert_eq!(
+                header(&status, "location"),
+                vec!["https://example.com/thanks"]
+            );
+            assert!(matches!(redirect(""), Err(Error::InvalidLocation(_))));
+            assert!(matches!(
+                redirect("/thanks\r\nSet-Cookie: a=b"),
+                Err(Error::InvalidLocation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn common_responses() {
+        assert!(Status::no_content().bytes().is_empty());
+
+        let status = Status::too_many_requests(Duration::from_secs(30)).unwrap();
+        assert_eq!(status.code(), &StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(header(&status, "retry-after"), vec!["30"]);
+
+        let status = Status::unauthorized("Basic", &[("realm", "forms")]).unwrap();
+        assert_eq!(
+            header(&status, "www-authenticate"),
+            vec![r#"Basic realm="forms""#]
+        );
+        assert!(Status::unauthorized("", &[]).is_err());
+
+        let status = Status::method_not_allowed(&[Method::GET, Method::POST, Method::GET]).unwrap();
+        assert_eq!(status.code(), &StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(header(&status, "allow"), vec!["GET, POST"]);
+        assert!(Status::method_not_allowed(&[]).is_err());
+    }
+
+    #[test]
+    fn first_error_is_kept() {
+        let err = Status::builder(StatusCode::OK)
+            .content_type("nope")
+            .header("x-ok", "fine")
+            .location("also nope")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidContentType(_)));
+    }
+}
+
+/// An error encountered while building a `Status`.
+#[derive(Debug)]
+pub enum Error {
+    /// A header name is not a valid HTTP header name.
+    InvalidHeaderName(String),
+    /// A header value contains characters that are not allowed in headers.
+    /// The name of the header is included.
+    InvalidHeaderValue(String),
+    /// A content type is not a valid media type.
+    InvalidContentType(String),
+    /// A `Location` is not a valid URI reference.
+    InvalidLocation(String),
+    /// A cookie's name, value, or attributes are not valid.
+    InvalidCookie(String),
+    /// An authentication challenge's scheme or parameters are not valid.
+    InvalidChallenge(String),
+    /// A body could not be serialized as JSON.
+    Json(serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidHeaderName(name) => write!(f, "invalid header name {:?}", name),
+            Error::InvalidHeaderValue(name) => write!(f, "invalid value for header {}", name),
+            Error::InvalidContentType(value) => write!(f, "invalid content type {:?}", value),
+            Error::InvalidLocation(value) => write!(f, "invalid location {:?}", value),
+            Error::InvalidCookie(msg) => write!(f, "invalid cookie: {}", msg),
+            Error::InvalidChallenge(msg) => write!(f, "invalid challenge: {}", msg),
+            Error::Json(err) => write!(f, "could not serialize body: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Returns whether `value` is a `token`, as defined by RFC 9110.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None"),
+        }
+    }
+}
+
+/// A cookie to set with a `Set-Cookie` header, as described by RFC 6265.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Creates a session cookie without any attributes.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// Sets the `Path` attribute.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets the `Domain` attribute.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Sets the `Max-Age` attribute, in whole seconds.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets the `Secure` attribute.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Sets the `HttpOnly` attribute.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// Sets the `SameSite` attribute.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Returns the cookie as the value of a `Set-Cookie` header.
+    fn to_header_value(&self) -> Result<HeaderValue, Error> {
+        if !is_token(&self.name) {
+            return Err(Error::InvalidCookie(format!(
+                "invalid name {:?}",
+                self.name
+            )));
+        }
+        // cookie-octet from RFC 6265, optionally in double quotes
+        let value = self
+            .value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(&self.value);
+        let is_cookie_octet = |byte: u8| {
+            byte == 0x21
+                || (0x23..=0x2b).contains(&byte)
+                || (0x2d..=0x3a).contains(&byte)
+                || (0x3c..=0x5b).contains(&byte)
+                || (0x5d..=0x7e).contains(&byte)
+        };
+        if !value.bytes().all(is_cookie_octet) {
+            return Err(Error::InvalidCookie(format!(
+                "invalid value for {}",
+                self.name
+            )));
+        }
+
+        let mut cookie = format!("{}={}", self.name, self.value);
+        for (attr, value) in &[("Path", &self.path), ("Domain", &self.domain)] {
+            if let Some(value) = value {
+                if value.contains(&[';', '\r', '\n'][..]) {
+                    return Err(Error::InvalidCookie(format!("invalid {} attribute", attr)));
+                }
+                cookie.push_str(&format!("; {}={}", attr, value));
+            }
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.push_str(&format!("; SameSite={}", same_site));
+        }
+
+        HeaderValue::from_str(&cookie)
+            .map_err(|_| Error::InvalidHeaderValue(String::from("set-cookie")))
+    }
+}
+
+/// A builder for `Status`, created with `Status::builder`.
+///
+/// Each method validates its arguments. The first invalid argument is kept and
+/// returned by `build`, and any later calls are ignored, so the builder can be
+/// chained without checking each step.
+#[derive(Debug)]
+pub struct StatusBuilder {
+    code: StatusCode,
+    body: Bytes,
+    headers: HeaderMap,
+    error: Option<Error>,
+}
+
+impl StatusBuilder {
+    fn new(code: StatusCode) -> Self {
+        StatusBuilder {
+            code,
+            body: Bytes::new(),
+            headers: HeaderMap::new(),
+            error: None,
+        }
+    }
+
+    /// Runs `f` on the headers unless an earlier call failed, and keeps the
+    /// error if `f` fails.
+    fn with_headers(mut self, f: impl FnOnce(&mut HeaderMap) -> Result<(), Error>) -> Self {
+        if self.error.is_none() {
+            if let Err(err) = f(&mut self.headers) {
+                self.error = Some(err);
+            }
+        }
+        self
+    }
+
+    /// Appends a header, keeping any earlier values with the same name.
+    pub fn header(self, name: &str, value: &str) -> Self {
+        self.with_headers(|headers| {
+            let name = HeaderName::try_from(name)
+                .map_err(|_| Error::InvalidHeaderName(String::from(name)))?;
+            let value = HeaderValue::try_from(value)
+                .map_err(|_| Error::InvalidHeaderValue(name.to_string()))?;
+            headers.append(name, value);
+            Ok(())
+        })
+    }
+
+    /// Sets the `Content-Type`, which must be a valid media type.
+    pub fn content_type(self, content_type: &str) -> Self {
+        self.with_headers(|headers| {
+            let invalid = || Error::InvalidContentType(String::from(content_type));
+            content_type.parse::<mime::Mime>().map_err(|_| invalid())?;
+            let value = HeaderValue::try_from(content_type).map_err(|_| invalid())?;
+            headers.insert(header::CONTENT_TYPE, value);
+            Ok(())
+        })
+    }
+
+    /// Sets the body.
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets the body to `value` serialized as JSON, and the `Content-Type` to
+    /// `application/json` unless one was already set.
+    pub fn json<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => self.body = body.into(),
+            Err(err) => {
+                self.error.get_or_insert(Error::Json(err));
+                return self;
+            }
+        }
+        self.with_headers(|headers| {
+            headers
+                .entry(header::CONTENT_TYPE)
+                .or_insert_with(|| HeaderValue::from_static("application/json"));
+            Ok(())
+        })
+    }
+
+    /// Sets the `Cache-Control` directives, e.g. `no-store` or
+    /// `public, max-age=3600`.
+    pub fn cache_control(self, directives: &str) -> Self {
+        self.with_headers(|headers| {
+            let value = HeaderValue::try_from(directives)
+                .map_err(|_| Error::InvalidHeaderValue(String::from("cache-control")))?;
+            headers.insert(header::CACHE_CONTROL, value);
+            Ok(())
+        })
+    }
+
+    /// Sets the `Location`, which must be an absolute URI or a path.
+    pub fn location(self, location: &str) -> Self {
+        self.with_headers(|headers| {
+            let invalid = || Error::InvalidLocation(String::from(location));
+            location.parse::<Uri>().map_err(|_| invalid())?;
+            let value = HeaderValue::try_from(location).map_err(|_| invalid())?;
+            headers.insert(header::LOCATION, value);
+            Ok(())
+        })
+    }
+
+    /// Sets `Retry-After` to `delay`, rounded up to whole seconds.
+    pub fn retry_after(self, delay: Duration) -> Self {
+        let secs = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+        self.with_headers(|headers| {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            Ok(())
+        })
+    }
+
+    /// Appends a `Set-Cookie` header for `cookie`.
+    pub fn cookie(self, cookie: Cookie) -> Self {
+        self.with_headers(|headers| {
+            headers.append(header::SET_COOKIE, cookie.to_header_value()?);
+            Ok(())
+        })
+    }
+
+    /// Appends a `WWW-Authenticate` challenge for `scheme` with the given
+    /// parameters, whose values are sent as quoted strings.
+    pub fn www_authenticate(self, scheme: &str, params: &[(&str, &str)]) -> Self {
+        self.with_headers(|headers| {
+            if !is_token(scheme) {
+                return Err(Error::InvalidChallenge(format!(
+                    "invalid scheme {:?}",
+                    scheme
+                )));
+            }
+            let mut challenge = String::from(scheme);
+            for (idx, (name, value)) in params.iter().enumerate() {
+                if !is_token(name) {
+                    return Err(Error::InvalidChallenge(format!(
+                        "invalid parameter name {:?}",
+                        name
+                    )));
+                }
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+                let sep = if idx == 0 { " " } else { ", " };
+                challenge.push_str(&format!("{}{}=\"{}\"", sep, name, value));
+            }
+            let value = HeaderValue::try_from(challenge)
+                .map_err(|_| Error::InvalidHeaderValue(String::from("www-authenticate")))?;
+            headers.append(header::WWW_AUTHENTICATE, value);
+            Ok(())
+        })
+    }
+
+    /// Returns the built `Status`, or the first invalid argument.
+    pub fn build(self) -> Result<Status<Bytes>, Error> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let mut status = Status::with_data(self.code, self.body);
+        status.h = self.headers;
+        Ok(status)
+    }
+}
+
+impl Status {
+    /// Returns a builder for a Status with the given code.
+    pub fn builder(code: StatusCode) -> StatusBuilder {
+        StatusBuilder::new(code)
+    }
+
+    /// Create a `301 Moved Permanently` redirect to `location`.
+    pub fn moved_permanently(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::MOVED_PERMANENTLY)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `302 Found` redirect to `location`.
+    pub fn found(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::FOUND)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `303 See Other` redirect to `location`, which clients follow
+    /// with a `GET`. This is the usual answer to a submitted form.
+    pub fn see_other(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::SEE_OTHER)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `307 Temporary Redirect` to `location`, which clients follow
+    /// with the original method and body.
+    pub fn temporary_redirect(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::TEMPORARY_REDIRECT)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `308 Permanent Redirect` to `location`, which clients follow
+    /// with the original method and body.
+    pub fn permanent_redirect(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::PERMANENT_REDIRECT)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `201 Created` pointing at the new resource's `location`.
+    pub fn created(location: &str) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::CREATED)
+            .location(location)
+            .build()
+    }
+
+    /// Create a `204 No Content`.
+    pub fn no_content() -> Status<Empty> {
+        Status::new(StatusCode::NO_CONTENT)
+    }
+
+    /// Create a `429 Too Many Requests` asking the client to wait
+    /// `retry_after`, rounded up to whole seconds, before trying again.
+    pub fn too_many_requests(retry_after: Duration) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::TOO_MANY_REQUESTS)
+            .retry_after(retry_after)
+            .build()
+    }
+
+    /// Create a `401 Unauthorized` with a `WWW-Authenticate` challenge for
+    /// `scheme`, e.g. `Status::unauthorized("Basic", &[("realm", "forms")])`.
+    pub fn unauthorized(scheme: &str, params: &[(&str, &str)]) -> Result<Status<Bytes>, Error> {
+        Status::builder(StatusCode::UNAUTHORIZED)
+            .www_authenticate(scheme, params)
+            .build()
+    }
+
+    /// Create a `405 Method Not Allowed` whose `Allow` header lists `allowed`,
+    /// without duplicates. At least one method must be allowed.
+    pub fn method_not_allowed(allowed: &[Method]) -> Result<Status<Bytes>, Error> {
+        if allowed.is_empty() {
+            return Err(Error::InvalidHeaderValue(String::from("allow")));
+        }
+        let mut methods: Vec<&str> = Vec::with_capacity(allowed.len());
+        for method in allowed {
+            if !methods.contains(&method.as_str()) {
+                methods.push(method.as_str());
+            }
+        }
+        Status::builder(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", &methods.join(", "))
+            .build()
+    }
+}
@@ -0,0 +1,30 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The response header that carries a correlation ID.
+pub const HEADER: &str = "x-correlation-id";
+
+/// The longest correlation ID accepted from callers.
+const MAX_LEN: usize = 128;
+
+/// Returns a new random correlation ID of 16 hex digits. The IDs are unique
+/// enough to find a log line, but are not meant to be unguessable.
+pub(crate) fn generate() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos());
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(nanos);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns whether `id` can be sent in a header and a plain-text body: up to
+/// 128 visible ASCII characters.
+pub(crate) fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_LEN && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
@@ -0,0 +1,175 @@
+use crate::{Status, StatusCode};
+use bytes::Bytes;
+use http::header::{self, HeaderValue};
+use serde::Serialize;
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::ser::Error as _;
+    use serde::Serializer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug, PartialEq, Serialize)]
+    struct Receipt {
+        id: u32,
+        email: String,
+    }
+
+    /// Counts how often it has been serialized.
+    #[derive(Clone, Debug, Default)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Serialize for Counted {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            serializer.serialize_unit()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("nope"))
+        }
+    }
+
+    #[test]
+    fn with_json_keeps_typed_data() {
+        let receipt = Receipt {
+            id: 7,
+            email: String::from("jane@example.com"),
+        };
+        let status = Status::with_json(StatusCode::CREATED, receipt.clone());
+
+        assert_eq!(status.data().id, 7);
+        assert_eq!(status.data().0, receipt);
+        assert_eq!(status.bytes(), br#"{"id":7,"email":"jane@example.com"}"#);
+        assert_eq!(
+            status.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            status.message(),
+            Some(r#"{"id":7,"email":"jane@example.com"}"#)
+        );
+    }
+
+    #[test]
+    fn serialization_is_lazy_and_cached() {
+        let counted = Counted::default();
+        let status = Status::with_json(StatusCode::OK, counted.clone());
+        assert_eq!(counted.0.load(Ordering::SeqCst), 0);
+
+        assert_eq!(status.bytes(), b"null");
+        assert_eq!(status.bytes(), b"null");
+        assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn failed_serialization_is_an_error() {
+        assert!(Json(Unserializable).to_bytes().is_err());
+
+        let status = Status::with_json(StatusCode::OK, Unserializable);
+        assert_eq!(status.bytes(), b"");
+
+        assert!(Status::try_with_json(StatusCode::OK, Unserializable).is_err());
+    }
+
+    #[test]
+    fn try_with_json_serializes_once() {
+        let counted = Counted::default();
+        let status = Status::try_with_json(StatusCode::OK, counted.clone()).unwrap();
+        assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+
+        assert_eq!(status.bytes(), b"null");
+        assert_eq!(
+            status.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(counted.0.load(Ordering::SeqCst), 1);
+    }
+}
+
+/// Data for a `Status` that is serialized as JSON when its bytes are first
+/// needed.
+///
+/// `Status::data()` returns the wrapper, which dereferences to the wrapped
+/// value, and `Status::bytes()` returns its JSON encoding. Use
+/// `Status::with_json` to also set the `Content-Type` to `application/json`.
+///
+/// As `StatusData` conversions cannot fail, a value that cannot be serialized
+/// results in an empty body. Use `Status::try_with_json` to serialize the
+/// value up front and handle the error instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> Json<T> {
+    /// Serializes the wrapped value.
+    pub fn to_bytes(&self) -> Result<Bytes, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Bytes::from)
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Serialize> From<Json<T>> for Bytes {
+    fn from(json: Json<T>) -> Bytes {
+        json.to_bytes().unwrap_or_default()
+    }
+}
+
+impl Status {
+    /// Create a new Status whose data is `value`, serialized as JSON when the
+    /// Status is sent, with a `Content-Type` of `application/json`.
+    pub fn with_json<T>(code: StatusCode, value: T) -> Status<Json<T>>
+    where
+        T: Serialize + Clone + Debug + Send + Sync + 'static,
+    {
+        let mut status = Status::with_data(code, Json(value));
+        status.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        status
+    }
+
+    /// Like `with_json`, but serializes `value` immediately, returning the
+    /// error if it cannot be serialized instead of sending an empty body.
+    /// The serialized bytes are kept and sent as the body.
+    pub fn try_with_json<T>(
+        code: StatusCode,
+        value: T,
+    ) -> Result<Status<Json<T>>, serde_json::Error>
+    where
+        T: Serialize + Clone + Debug + Send + Sync + 'static,
+    {
+        let json = Json(value);
+        let bytes = json.to_bytes()?;
+        let status = Status::with_json(code, json.0);
+        let _ = status.data_bytes.set(bytes);
+        Ok(status)
+    }
+}
@@ -2,7 +2,7 @@ use bytes::Bytes;
 use http::header::{self, HeaderMap, HeaderValue};
 #[cfg(feature = "server-warp")]
 use http::response::Builder;
-pub use http::StatusCode;
+pub use http::{Method, StatusCode};
 #[cfg(feature = "server-warp")]
 use hyper::Body;
 /// This crate implements a standalone datatype for HTTP status codes. `Status`
@@ -11,13 +11,36 @@ use hyper::Body;
 ///
 /// Currently, the only automatic conversion that is supported is for Warp.
 ///
+#[cfg(feature = "server-warp")]
+use std::any::Any;
 use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
 #[cfg(feature = "server-warp")]
 use warp::{
     reject::{self, Reject, Rejection},
     reply::{Reply, Response},
 };
 
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+pub mod builder;
+pub mod correlation;
+pub mod json;
+pub mod negotiate;
+pub mod problem;
+#[cfg(feature = "server-warp")]
+pub mod recover;
+#[cfg(feature = "server-warp")]
+mod report;
+pub mod response;
+
+pub use builder::{Cookie, SameSite, StatusBuilder};
+pub use json::Json;
+pub use negotiate::{Negotiator, Renderer};
+pub use problem::Problem;
+#[cfg(feature = "server-warp")]
+pub use recover::recover;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,6 +74,128 @@ mod tests {
         assert!(Status::new(StatusCode::IM_A_TEAPOT).headers().is_empty());
     }
 
+    #[test]
+    fn problem_json_status_has_message() {
+        let status = Status::with_problem(Problem::new(StatusCode::NOT_FOUND));
+        let message: serde_json::Value = serde_json::from_str(status.message().unwrap()).unwrap();
+        assert_eq!(message["title"], "Not Found");
+    }
+
+    #[test]
+    fn source_is_kept_but_not_sent() {
+        use std::error::Error as _;
+
+        // The conversion `?` uses
+        let status = Status::<String>::from(std::io::Error::other("cannot read /etc/secret.toml"));
+        assert_eq!(status.code(), &StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(status.bytes().is_empty());
+        assert!(!status.to_string().contains("secret"));
+        assert!(format!("{:?}", status).contains("secret"));
+
+        let source = status.source().unwrap();
+        assert_eq!(source.to_string(), "cannot read /etc/secret.toml");
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+        let status = status.into_bytes();
+        assert!(status.source().is_some());
+        assert_eq!(
+            status,
+            Status::with_data(StatusCode::INTERNAL_SERVER_ERROR, Bytes::new())
+        );
+    }
+
+    #[test]
+    fn internal_error_from_any_error() {
+        use std::error::Error as _;
+
+        let status = "not a number"
+            .parse::<u8>()
+            .map_err(Status::internal_error)
+            .unwrap_err();
+        assert_eq!(status.code(), &StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(status.source().is_some());
+        assert!(Status::new(StatusCode::NOT_FOUND).source().is_none());
+    }
+
+    #[test]
+    fn server_errors_get_correlation_ids() {
+        let status =
+            Status::with_message(StatusCode::BAD_GATEWAY, String::from("upstream timed out"));
+        let id = status.correlation_id().unwrap().to_string();
+        assert_eq!(
+            status.to_string(),
+            format!("502 Bad Gateway\nreference {}", id)
+        );
+        assert_eq!(status.clone().into_bytes().correlation_id(), Some(&id[..]));
+
+        assert!(Status::new(StatusCode::NOT_FOUND)
+            .correlation_id()
+            .is_none());
+        assert_ne!(
+            Status::new(StatusCode::INTERNAL_SERVER_ERROR).correlation_id(),
+            Status::new(StatusCode::INTERNAL_SERVER_ERROR).correlation_id()
+        );
+    }
+
+    #[test]
+    fn generated_correlation_ids_are_unique_and_valid() {
+        let ids: Vec<String> = (0..1000).map(|_| correlation::generate()).collect();
+        for id in &ids {
+            assert_eq!(id.len(), 16);
+            assert!(correlation::is_valid(id));
+        }
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn correlation_id_can_be_replaced() {
+        let status = Status::new(StatusCode::INTERNAL_SERVER_ERROR).with_correlation_id("req-42");
+        assert_eq!(status.correlation_id(), Some("req-42"));
+
+        for invalid in &["", "has space", "line\nbreak", &"a".repeat(129)] {
+            let status = status.clone().with_correlation_id(invalid);
+            assert_eq!(status.correlation_id(), Some("req-42"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn server_error_response_is_masked() {
+        let status = Status::with_message(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("database password is hunter2"),
+        );
+        let id = status.correlation_id().unwrap().to_string();
+
+        let response = Response::from(status);
+        assert_eq!(
+            response.headers().get(correlation::HEADER).unwrap(),
+            id.as_str()
+        );
+        let body = futures::executor::block_on(hyper::body::to_bytes(response.into_body()));
+        assert_eq!(
+            body.unwrap(),
+            format!("500 Internal Server Error\nreference {}", id)
+        );
+
+        let status = Status::with_json(StatusCode::SERVICE_UNAVAILABLE, vec![1, 2]);
+        let response = Response::from(status);
+        assert!(response.headers().contains_key(correlation::HEADER));
+        let body = futures::executor::block_on(hyper::body::to_bytes(response.into_body()));
+        assert_eq!(body.unwrap(), "[1,2]");
+    }
+
+    #[test]
+    #[cfg(feature = "server-warp")]
+    fn client_error_response_has_no_correlation_id() {
+        let status = Status::new(StatusCode::NOT_FOUND).with_correlation_id("req-42");
+        let response = Response::from(status);
+        assert!(!response.headers().contains_key(correlation::HEADER));
+    }
+
     #[test]
     fn server_error_does_not_contain_error_message() {
         let server_msg = "foobar";
@@ -81,13 +226,394 @@ mod tests {
         let status = Status::with_data(StatusCode::IM_A_TEAPOT, data.clone());
 
         let rej = reject::Rejection::from(status.clone());
-        let rej_status = rej.find::<Status<Vec<u8>>>().unwrap();
+        assert!(Status::<Vec<u8>>::rejection_is_status(&rej));
+        assert!(!Status::<String>::rejection_is_status(&rej));
 
-        assert_eq!(&status, rej_status);
+        let rej_status = Status::<Vec<u8>>::recover(rej).unwrap();
+        assert_eq!(status, rej_status);
     }
 
     // - 5xx status does not reveal error message to client
     // - Correctly implements Warp's error type
+
+    mod response_conversions {
+        use super::*;
+        #[cfg(feature = "hyper")]
+        use crate::response::Error;
+        #[cfg(feature = "hyper")]
+        use hyper::Body;
+
+        fn get_response() -> http::Response<Bytes> {
+            http::Response::builder()
+                .status(StatusCode::CONFLICT)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::SET_COOKIE, "a=1")
+                .header(header::SET_COOKIE, "b=2")
+                .body(Bytes::from_static(br#"{"taken":true}"#))
+                .unwrap()
+        }
+
+        #[test]
+        fn response_is_preserved_exactly() {
+            let response = get_response();
+            let status = Status::from(get_response());
+
+            assert_eq!(status.code(), &StatusCode::CONFLICT);
+            assert_eq!(status.headers(), response.headers());
+            assert_eq!(status.bytes(), response.body().as_ref());
+            assert_eq!(status.correlation_id(), None);
+        }
+
+        #[test]
+        fn server_error_keeps_its_correlation_id() {
+            let response = http::Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .header(correlation::HEADER, "abc123")
+                .body(Bytes::new())
+                .unwrap();
+            let status = Status::from(response);
+            assert_eq!(status.correlation_id(), Some("abc123"));
+
+            let response = http::Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Bytes::new())
+                .unwrap();
+            assert_eq!(Status::from(response).correlation_id(), None);
+        }
+
+        #[cfg(feature = "hyper")]
+        #[test]
+        fn hyper_response_is_read_within_limit() {
+            let (parts, body) = get_response().into_parts();
+            let response = hyper::Response::from_parts(parts, Body::from(body.clone()));
+
+            let status =
+                futures::executor::block_on(Status::from_hyper_response(response, body.len()))
+                    .unwrap();
+            assert_eq!(status.code(), &StatusCode::CONFLICT);
+            assert_eq!(status.headers(), get_response().headers());
+            assert_eq!(status.bytes(), body.as_ref());
+        }
+
+        #[cfg(feature = "hyper")]
+        #[test]
+        fn hyper_response_over_limit_is_an_error() {
+            let (parts, body) = get_response().into_parts();
+            let response = hyper::Response::from_parts(parts, Body::from(body.clone()));
+
+            let result =
+                futures::executor::block_on(Status::from_hyper_response(response, body.len() - 1));
+            match result {
+                Err(Error::TooLarge(limit)) => assert_eq!(limit, body.len() - 1),
+                other => panic!("expected Error::TooLarge, got {:?}", other),
+            }
+        }
+
+        #[cfg(feature = "hyper")]
+        #[test]
+        fn chunked_hyper_response_over_limit_is_an_error() {
+            let (mut sender, body) = Body::channel();
+            let response = hyper::Response::new(body);
+
+            let result = futures::executor::block_on(async {
+                let send = async {
+                    sender.send_data(Bytes::from_static(b"0123")).await.unwrap();
+                    sender.send_data(Bytes::from_static(b"4567")).await.unwrap();
+                    drop(sender);
+                };
+                let (_, result) = futures::join!(send, Status::from_hyper_response(response, 6));
+                result
+            });
+            assert!(matches!(result, Err(Error::TooLarge(6))));
+        }
+    }
+
+    #[cfg(feature = "server-warp")]
+    mod recover_rejections {
+        use super::*;
+        use bytes::Bytes;
+        use warp::reject::Rejection;
+        use warp::reply::Response;
+        use warp::Filter;
+
+        fn body_of(resp: Response) -> Bytes {
+            futures::executor::block_on(hyper::body::to_bytes(resp.into_body())).unwrap()
+        }
+
+        #[test]
+        fn finds_status_of_any_common_data_type() {
+            let rej = Rejection::from(Status::with_message(
+                StatusCode::CONFLICT,
+                String::from("already exists"),
+            ));
+            let status = Status::from_rejection(&rej);
+            assert_eq!(status.code(), &StatusCode::CONFLICT);
+            assert_eq!(status.message(), Some("already exists"));
+
+            let rej = Rejection::from(Status::new(StatusCode::GONE));
+            assert_eq!(Status::from_rejection(&rej).code(), &StatusCode::GONE);
+
+            let rej = Rejection::from(Status::with_data(StatusCode::IM_A_TEAPOT, vec![1u8, 2]));
+            assert_eq!(Status::from_rejection(&rej).bytes(), &[1, 2]);
+
+            let rej = Rejection::from(Status::with_json(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                serde_json::json!({ "field": "email" }),
+            ));
+            let status = Status::from_rejection(&rej);
+            assert_eq!(status.code(), &StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(status.bytes(), br#"{"field":"email"}"#);
+            assert_eq!(
+                status.headers().get(header::CONTENT_TYPE).unwrap(),
+                "application/json"
+            );
+        }
+
+        #[test]
+        fn finds_status_of_other_data_types() {
+            let status = Status::with_json(StatusCode::CONFLICT, vec![1, 2]);
+
+            let rej = Rejection::from(status.clone());
+            let recovered = Status::from_rejection(&rej);
+            assert_eq!(recovered.code(), &StatusCode::CONFLICT);
+            assert_eq!(recovered.bytes(), b"[1,2]");
+            assert_eq!(recovered, status.clone().into_bytes());
+
+            assert_eq!(Status::recover(rej).unwrap(), status);
+        }
+
+        #[test]
+        fn keeps_correlation_id_of_found_status() {
+            let original = Status::new(StatusCode::BAD_GATEWAY);
+            let id = original.correlation_id().unwrap().to_owned();
+
+            let status = Status::from_rejection(&Rejection::from(original));
+            assert_eq!(status.correlation_id(), Some(id.as_str()));
+        }
+
+        #[test]
+        fn translates_not_found() {
+            let status = Status::from_rejection(&warp::reject::not_found());
+            assert_eq!(status.code(), &StatusCode::NOT_FOUND);
+        }
+
+        #[test]
+        fn translates_warp_rejections() {
+            let rt = |filter_result: Result<(), Rejection>| {
+                Status::from_rejection(&filter_result.unwrap_err())
+            };
+
+            let rej = futures::executor::block_on(
+                warp::test::request().method("GET").filter(&warp::post()),
+            );
+            assert_eq!(rt(rej).code(), &StatusCode::METHOD_NOT_ALLOWED);
+
+            let rej = futures::executor::block_on(
+                warp::test::request().filter(&warp::header::<String>("x-api-key").map(|_| ())),
+            );
+            let status = rt(rej);
+            assert_eq!(status.code(), &StatusCode::BAD_REQUEST);
+            assert_eq!(
+                status.message(),
+                Some(r#"Missing request header "x-api-key""#)
+            );
+
+            let rej = futures::executor::block_on(
+                warp::test::request()
+                    .body("0123456789")
+                    .filter(&warp::body::content_length_limit(4)),
+            );
+            assert_eq!(rt(rej).code(), &StatusCode::PAYLOAD_TOO_LARGE);
+
+            let rej = futures::executor::block_on(
+                warp::test::request()
+                    .header("content-type", "text/plain")
+                    .body("{}")
+                    .filter(&warp::body::json::<serde_json::Value>().map(|_| ())),
+            );
+            assert_eq!(rt(rej).code(), &StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+
+        #[test]
+        fn unknown_rejection_is_a_hidden_server_error() {
+            #[derive(Debug)]
+            struct Unknown;
+            impl warp::reject::Reject for Unknown {}
+
+            let status = Status::from_rejection(&warp::reject::custom(Unknown));
+            assert_eq!(status.code(), &StatusCode::INTERNAL_SERVER_ERROR);
+            assert!(status.correlation_id().is_some());
+
+            let resp = futures::executor::block_on(recover(warp::reject::custom(Unknown))).unwrap();
+            let id = resp.headers().get(crate::correlation::HEADER).unwrap();
+            let expected = format!(
+                "500 Internal Server Error\nreference {}",
+                id.to_str().unwrap()
+            );
+            assert_eq!(body_of(resp), expected.as_bytes());
+        }
+
+        #[test]
+        fn recover_renders_through_filter() {
+            let route = warp::path("teapot")
+                .and_then(|| async {
+                    Err::<String, _>(Rejection::from(Status::with_message(
+                        StatusCode::IM_A_TEAPOT,
+                        String::from("short and stout"),
+                    )))
+                })
+                .recover(recover);
+
+            let resp =
+                futures::executor::block_on(warp::test::request().path("/teapot").reply(&route));
+            assert_eq!(resp.status(), StatusCode::IM_A_TEAPOT);
+            assert_eq!(resp.body().as_ref(), b"short and stout");
+
+            let resp =
+                futures::executor::block_on(warp::test::request().path("/kettle").reply(&route));
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_events {
+        use super::*;
+        use std::fmt::Debug as FmtDebug;
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Level, Metadata, Subscriber};
+
+        /// An event's level and fields.
+        type Recorded = (String, Vec<(String, String)>);
+
+        /// Collects the level and fields of every event.
+        #[derive(Default)]
+        struct Collector {
+            events: Mutex<Vec<Recorded>>,
+        }
+
+        struct Fields(Vec<(String, String)>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn FmtDebug) {
+                self.0
+                    .push((field.name().to_string(), format!("{:?}", value)));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.push((field.name().to_string(), value.to_string()));
+            }
+        }
+
+        impl Subscriber for &'static Collector {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields(Vec::new());
+                event.record(&mut fields);
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push((event.metadata().level().to_string(), fields.0));
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        fn record<T: StatusData>(status: Status<T>) -> Recorded {
+            let collector: &'static Collector = Box::leak(Box::default());
+            tracing::subscriber::with_default(collector, || {
+                let _ = Response::from(status);
+            });
+            let mut events = collector.events.lock().unwrap();
+            assert_eq!(events.len(), 1);
+            events.pop().unwrap()
+        }
+
+        fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        }
+
+        #[test]
+        fn server_errors_record_hidden_details() {
+            let status = Status::with_message(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                String::from("pool exhausted"),
+            )
+            .with_source(std::io::Error::other("connection refused"));
+            let id = status.correlation_id().unwrap().to_string();
+
+            let (level, fields) = record(status);
+            assert_eq!(level, Level::ERROR.to_string());
+            assert_eq!(field(&fields, "code"), Some("500"));
+            assert_eq!(field(&fields, "status_message"), Some("pool exhausted"));
+            assert_eq!(field(&fields, "source"), Some("connection refused"));
+            assert_eq!(field(&fields, "correlation_id"), Some(id.as_str()));
+        }
+
+        #[test]
+        fn level_follows_status_class() {
+            let (level, fields) = record(Status::with_message(
+                StatusCode::NOT_FOUND,
+                String::from("no such form"),
+            ));
+            assert_eq!(level, Level::WARN.to_string());
+            assert_eq!(field(&fields, "status_message"), Some("no such form"));
+
+            assert_eq!(
+                record(Status::new(StatusCode::SEE_OTHER)).0,
+                Level::DEBUG.to_string()
+            );
+            assert_eq!(
+                record(Status::new(StatusCode::OK)).0,
+                Level::DEBUG.to_string()
+            );
+            assert_eq!(
+                record(Status::new(StatusCode::CONTINUE)).0,
+                Level::TRACE.to_string()
+            );
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn result_conversion_follows_code(status in any::<Status<Bytes>>()) {
+                let is_ok = status.code().as_u16() < 400;
+                prop_assert_eq!(Result::from(status).is_ok(), is_ok);
+            }
+
+            #[test]
+            fn display_hides_server_error_messages(status in any::<Status<Bytes>>()) {
+                if status.code().is_server_error() {
+                    prop_assert_eq!(
+                        status.to_string(),
+                        format!("{}\nreference {}", status.code(), status.correlation_id().unwrap())
+                    );
+                }
+            }
+        }
+    }
 }
 
 /// An enumerated list of possible errors returned by this crate and related data.
@@ -109,7 +635,7 @@ pub trait StatusData: Into<Bytes> + StatusInnerData {}
 impl<T: Into<Bytes> + StatusInnerData> StatusData for T {}
 
 /// An empty type used by a Status without associated data.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Empty;
 
 impl Into<Bytes> for Empty {
@@ -123,17 +649,33 @@ impl Into<Bytes> for Empty {
 /// Code that creates a new instance of Status should set any related response
 /// headers before returning it.
 // TODO: Genericize the data member into anything that can be converted into bytes?
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// The data is only converted into bytes the first time they are needed.
+#[derive(Clone, Debug)]
 pub struct Status<T = Empty>
 where
     T: StatusData,
 {
     c: StatusCode,
     data: T,
-    data_bytes: Bytes,
+    data_bytes: OnceLock<Bytes>,
     h: HeaderMap<HeaderValue>,
+    source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+    correlation_id: Option<String>,
+}
+
+// The cached bytes are derived from the data, so they are left out of
+// comparisons: a Status is equal to a copy whose bytes were not needed yet.
+// The source error is left out as errors cannot be compared, and the
+// correlation ID as it is random.
+impl<T: StatusData + PartialEq> PartialEq for Status<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.c == other.c && self.data == other.data && self.h == other.h
+    }
 }
 
+impl<T: StatusData + Eq> Eq for Status<T> {}
+
 impl Status {
     /// Create a new Status without any associated data. This will be converted to
     /// the specified status code with associated headers and no body.
@@ -141,8 +683,10 @@ impl Status {
         Status {
             c: code,
             data: Empty {},
-            data_bytes: Bytes::new(),
+            data_bytes: OnceLock::new(),
             h: HeaderMap::new(),
+            source: None,
+            correlation_id: new_correlation_id(code),
         }
     }
 
@@ -157,14 +701,24 @@ impl Status {
         status
     }
 
+    /// Create a new `500 Internal Server Error` caused by `err`, for errors
+    /// that have no `From` conversion, e.g. `.map_err(Status::internal_error)`.
+    pub fn internal_error(
+        err: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Status<Empty> {
+        Status::new(StatusCode::INTERNAL_SERVER_ERROR).with_source(err)
+    }
+
     /// Create a new Status with associated arbitrary data. Useful for
     /// returning a struct that can be serialized into e.g. JSON.
     pub fn with_data<T: StatusData>(code: StatusCode, data: T) -> Status<T> {
         Status {
             c: code,
-            data: data.clone(),
-            data_bytes: data.into(),
+            data,
+            data_bytes: OnceLock::new(),
             h: HeaderMap::new(),
+            source: None,
+            correlation_id: new_correlation_id(code),
         }
     }
 }
@@ -180,7 +734,7 @@ impl<T: StatusData> Status<T> {
         // If there is data and it can successfully be parsed as a string,
         // return the parsed string. Otherwise, return None, ignoring any
         // errors while parsing.
-        std::str::from_utf8(self.data_bytes.as_ref()).ok()
+        std::str::from_utf8(self.bytes()).ok()
     }
 
     /// Attempts to parse the data contained in this Status as a &str.
@@ -196,7 +750,9 @@ impl<T: StatusData> Status<T> {
                     Some(mime_type) => match mime_type.type_() {
                         mime::TEXT => self.data_as_message(),
                         _ => {
-                            if mime_type == mime::APPLICATION_JSON {
+                            if mime_type == mime::APPLICATION_JSON
+                                || mime_type.suffix() == Some(mime::JSON)
+                            {
                                 self.data_as_message()
                             } else {
                                 None
@@ -216,7 +772,76 @@ impl<T: StatusData> Status<T> {
     }
 
     pub fn bytes(&self) -> &[u8] {
-        &self.data_bytes
+        self.data_bytes.get_or_init(|| self.data.clone().into())
+    }
+
+    /// Attaches the error that caused this Status, which is returned by
+    /// `source()` and included in its `Debug` output for logging. The source
+    /// is never sent to the client.
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        self.source = Some(Arc::from(source.into()));
+        self
+    }
+
+    /// Returns the ID that links this Status to the server's logs.
+    ///
+    /// Every `5xx` Status gets a randomly generated ID. It is sent to the
+    /// client in the `X-Correlation-ID` header and in place of the hidden
+    /// message, and logged along with the message and source.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Replaces the correlation ID, e.g. with a request ID assigned by a
+    /// proxy. IDs that are empty, longer than 128 characters, or contain
+    /// anything but visible ASCII are ignored.
+    ///
+    /// Only server errors send the ID to the client and log it.
+    pub fn with_correlation_id(mut self, id: &str) -> Self {
+        if correlation::is_valid(id) {
+            self.correlation_id = Some(String::from(id));
+        }
+        self
+    }
+
+    /// Converts this Status into one whose data is its serialized bytes,
+    /// keeping the code, headers, source, and correlation ID.
+    pub fn into_bytes(self) -> Status<Bytes> {
+        let source = self.source.clone();
+        let correlation_id = self.correlation_id.clone();
+        let (code, headers, bytes) = self.into_parts();
+        let mut status = Status::with_data(code, bytes);
+        status.h = headers;
+        status.source = source;
+        status.correlation_id = correlation_id;
+        status
+    }
+
+    #[cfg(feature = "server-warp")]
+    /// Returns whether the body is plain text, or empty and without a
+    /// `Content-Type`.
+    fn has_plain_text_body(&self) -> bool {
+        match self.h.get(header::CONTENT_TYPE) {
+            None => self.bytes().is_empty() || self.data_as_message().is_some(),
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<mime::Mime>().ok())
+                .is_some_and(|mime_type| mime_type.essence_str() == "text/plain"),
+        }
+    }
+
+    /// Splits this Status into its code, headers, and serialized bytes,
+    /// reusing the bytes if they were already needed.
+    fn into_parts(self) -> (StatusCode, HeaderMap<HeaderValue>, Bytes) {
+        let bytes = match self.data_bytes.into_inner() {
+            Some(bytes) => bytes,
+            None => self.data.into(),
+        };
+        (self.c, self.h, bytes)
     }
 
     /// Gain an immutable view into the headers map.
@@ -229,46 +854,114 @@ impl<T: StatusData> Status<T> {
         &mut self.h
     }
 
+    #[cfg(feature = "server-warp")]
+    /// Finds this Status in the Rejection, whether it was converted into one
+    /// or passed to `warp::reject::custom`.
+    fn find_in(err: &Rejection) -> Option<&Self> {
+        err.find::<Rejected>()
+            .and_then(|rejected| rejected.original.downcast_ref())
+            .or_else(|| err.find::<Self>())
+    }
+
     #[cfg(feature = "server-warp")]
     /// Returns `true` if the warp Rejection is an instance of Status.
     pub fn rejection_is_status(err: &Rejection) -> bool {
-        err.find::<Self>().is_some()
+        Self::find_in(err).is_some()
     }
 
     #[cfg(feature = "server-warp")]
     /// Attempts to recover the Rejection as an instance of Status. Returns
-    /// Error::NotStatus if the Rejection does not implement Status.
+    /// Error::NotStatus if the Rejection does not implement Status. See
+    /// `Status::from_rejection` to recover a Status of any data type.
     // TODO: Example usage
     pub fn recover(err: Rejection) -> std::result::Result<Self, Error> {
-        err.find::<Self>()
-            .map(|stat| stat.clone())
-            .ok_or(Error::NotStatus(err))
+        Self::find_in(&err).cloned().ok_or(Error::NotStatus(err))
     }
 }
 
+/// A rejected Status. As warp can only search a Rejection for a known type,
+/// the Status is also kept as a `Status<Bytes>`, so it can be recovered
+/// without knowing its data type.
+#[cfg(feature = "server-warp")]
+#[derive(Debug)]
+pub(crate) struct Rejected {
+    pub(crate) status: Status<Bytes>,
+    original: Box<dyn Any + Send + Sync>,
+}
+
+#[cfg(feature = "server-warp")]
+impl Reject for Rejected {}
+
 #[cfg(feature = "server-warp")]
 impl<T: StatusData> From<Status<T>> for Rejection {
     fn from(status: Status<T>) -> Self {
-        reject::custom(status)
+        reject::custom(Rejected {
+            status: status.clone().into_bytes(),
+            original: Box::new(status),
+        })
     }
 }
 
 impl<T: StatusData> std::fmt::Display for Status<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.code().is_server_error() {
+            // Hide the message, but give the client something to report
+            return match self.correlation_id() {
+                None => write!(f, "{}", self.code()),
+                Some(id) => write!(f, "{}\nreference {}", self.code(), id),
+            };
+        }
+
         match self.message() {
             None => write!(f, "{}", self.code()),
-            Some(msg) => {
-                if self.code().as_u16() < 500 {
-                    write!(f, "{}\n{}", self.code(), msg)
-                } else {
-                    write!(f, "{}", self.code())
-                }
-            }
+            Some(msg) => write!(f, "{}\n{}", self.code(), msg),
         }
     }
 }
 
-impl<T: StatusData> std::error::Error for Status<T> {}
+/// Returns a correlation ID for a new Status if `code` is a server error.
+fn new_correlation_id(code: StatusCode) -> Option<String> {
+    if code.is_server_error() {
+        Some(correlation::generate())
+    } else {
+        None
+    }
+}
+
+impl<T: StatusData> std::error::Error for Status<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Implements `From` for error types that handlers commonly propagate with `?`,
+/// turning them into a `500 Internal Server Error` with the error as its
+/// source.
+macro_rules! internal_error_from {
+    ($($err:ty),* $(,)?) => {
+        $(
+            impl<T: StatusData + Default> From<$err> for Status<T> {
+                fn from(err: $err) -> Self {
+                    Status::with_data(StatusCode::INTERNAL_SERVER_ERROR, T::default())
+                        .with_source(err)
+                }
+            }
+        )*
+    };
+}
+
+internal_error_from!(
+    std::io::Error,
+    std::fmt::Error,
+    std::str::Utf8Error,
+    std::string::FromUtf8Error,
+    std::num::ParseIntError,
+    std::num::ParseFloatError,
+    http::Error,
+    serde_json::Error,
+);
 
 impl<T: StatusData> From<Status<T>> for Result<Status<T>, Status<T>> {
     fn from(s: Status<T>) -> Result<Status<T>, Status<T>> {
@@ -283,9 +976,34 @@ impl<T: StatusData> From<Status<T>> for Result<Status<T>, Status<T>> {
 #[cfg(feature = "server-warp")]
 impl<T: StatusData> From<Status<T>> for Response {
     fn from(s: Status<T>) -> Response {
-        let mut build = Builder::new().status(s.code());
+        report::report(&s);
+
+        // Server errors with a plain-text body, e.g. from `with_message`, get
+        // the generic text from `Display` instead, which names the reference.
+        let masked = if s.code().is_server_error() && s.has_plain_text_body() {
+            Some(s.to_string())
+        } else {
+            None
+        };
+        let correlation_id = s
+            .correlation_id()
+            .filter(|_| s.code().is_server_error())
+            .and_then(|id| HeaderValue::from_str(id).ok());
+
+        let (code, mut headers, mut body) = s.into_parts();
+        if let Some(text) = masked {
+            body = text.into();
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+        }
+        if let Some(id) = correlation_id {
+            headers.insert(correlation::HEADER, id);
+        }
+        let mut build = Builder::new().status(code);
 
-        for (key, val) in s.headers().iter() {
+        for (key, val) in headers.iter() {
             build = build.header(key, val)
         }
 
@@ -294,7 +1012,7 @@ impl<T: StatusData> From<Status<T>> for Response {
         // and HeaderName/HeaderValue types are taken directly from the same crate
         // that implements this Builder. Further, creating the hyper Body should
         // not error either.
-        build.body(Body::from(s.data_bytes)).unwrap()
+        build.body(Body::from(body)).unwrap()
     }
 }
 
@@ -0,0 +1,398 @@
+use crate::problem::PROBLEM_JSON;
+use crate::{Problem, Status, StatusData};
+use bytes::Bytes;
+use http::header::{self, HeaderValue};
+use mime::Mime;
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+#[cfg(feature = "server-warp")]
+use warp::{reject::Rejection, Filter};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StatusCode;
+
+    fn get_status() -> Status<String> {
+        Status::with_message(StatusCode::NOT_FOUND, String::from("No <such> page"))
+    }
+
+    fn content_type(status: &Status<Bytes>) -> &str {
+        status
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn missing_accept_uses_first_renderer() {
+        let status = Negotiator::default().render(get_status(), None);
+        assert_eq!(content_type(&status), "text/plain; charset=utf-8");
+        assert_eq!(status.bytes(), b"404 Not Found\nNo <such> page");
+        assert_eq!(status.headers().get(header::VARY).unwrap(), "accept");
+    }
+
+    #[test]
+    fn browsers_get_html() {
+        let accept = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+        let status = Negotiator::default().render(get_status(), Some(accept));
+        assert_eq!(content_type(&status), "text/html; charset=utf-8");
+
+        let body = std::str::from_utf8(status.bytes()).unwrap();
+        assert!(body.contains("<title>404 Not Found</title>"));
+        assert!(body.contains("No &lt;such&gt; page"));
+    }
+
+    #[test]
+    fn json_is_problem_details() {
+        let status = Negotiator::default().render(get_status(), Some("application/json"));
+        assert_eq!(content_type(&status), "application/json");
+
+        let value: serde_json::Value = serde_json::from_slice(status.bytes()).unwrap();
+        assert_eq!(value["status"], 404);
+        assert_eq!(value["detail"], "No <such> page");
+    }
+
+    #[test]
+    fn json_is_labelled_problem_json_when_accepted() {
+        for accept in &[
+            "application/problem+json",
+            "application/*",
+            "*/*;q=0.5, text/plain;q=0.1",
+        ] {
+            let status = Negotiator::default().render(get_status(), Some(accept));
+            assert_eq!(
+                content_type(&status),
+                "application/problem+json",
+                "{}",
+                accept
+            );
+        }
+
+        let accept = "application/json, application/problem+json;q=0";
+        let status = Negotiator::default().render(get_status(), Some(accept));
+        assert_eq!(content_type(&status), "application/json");
+    }
+
+    #[test]
+    fn json_keeps_attached_problem() {
+        let problem = Problem::new(StatusCode::CONFLICT).with_type("https://example.com/dup");
+        let status = Status::with_problem(problem.clone());
+        let status = Negotiator::default().render(status, Some("application/json"));
+        assert_eq!(status.bytes(), &problem.to_json()[..]);
+    }
+
+    #[test]
+    fn server_errors_hide_messages() {
+        let status = Status::with_message(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::from("database password is hunter2"),
+        );
+        let id = status.correlation_id().unwrap();
+        for accept in &["text/plain", "text/html", "application/json"] {
+            let rendered = Negotiator::default().render(status.clone(), Some(accept));
+            assert_eq!(rendered.correlation_id(), Some(id));
+            let body = std::str::from_utf8(rendered.bytes()).unwrap();
+            assert!(!body.contains("hunter2"), "{} leaked the message", accept);
+            assert!(body.contains(id), "{} is missing the reference", accept);
+        }
+    }
+
+    #[test]
+    fn quality_and_specificity_decide() {
+        let negotiator = Negotiator::default();
+        let select = |accept| negotiator.select(Some(accept)).unwrap().0.to_string();
+
+        assert_eq!(
+            select("application/json;q=0.5, text/html"),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            select("text/*;q=0.4, application/json;q=0.5"),
+            "application/json"
+        );
+        assert_eq!(select("*/*, text/plain;q=0"), "application/problem+json");
+        assert_eq!(select("TEXT/HTML"), "text/html; charset=utf-8");
+        assert_eq!(select("image/png"), "text/plain; charset=utf-8");
+        assert_eq!(
+            select("not a media type, text/html"),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn custom_renderers() {
+        let negotiator = Negotiator::new().renderer(
+            "application/vnd.example+csv".parse().unwrap(),
+            |status: &Status<Bytes>| Bytes::from(format!("code\n{}\n", status.code().as_u16())),
+        );
+        let status = negotiator.render(get_status(), Some("application/vnd.example+csv"));
+        assert_eq!(status.bytes(), b"code\n404\n");
+
+        assert!(Negotiator::new().select(None).is_none());
+        let status = Negotiator::new().render(get_status(), Some("text/html"));
+        assert_eq!(status, get_status().into_bytes());
+    }
+}
+
+/// Renders a `Status` as a body of one media type.
+///
+/// Any `Fn(&Status<Bytes>) -> Bytes` closure is a `Renderer`.
+pub trait Renderer: Send + Sync {
+    /// Returns the body to send for `status`. Renderers should not reveal
+    /// `status.message()` for `5xx` codes; `Display` for `Status` already
+    /// hides it.
+    fn render(&self, status: &Status<Bytes>) -> Bytes;
+}
+
+impl<F> Renderer for F
+where
+    F: Fn(&Status<Bytes>) -> Bytes + Send + Sync,
+{
+    fn render(&self, status: &Status<Bytes>) -> Bytes {
+        self(status)
+    }
+}
+
+/// Renders the status as `Display` does: the code, followed by the message
+/// for codes below 500.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlainText;
+
+impl Renderer for PlainText {
+    fn render(&self, status: &Status<Bytes>) -> Bytes {
+        status.to_string().into()
+    }
+}
+
+/// Renders the status as RFC 9457 problem details. A `Problem` that is already
+/// attached to the status is kept; otherwise the message, for codes below 500,
+/// becomes the problem's `detail`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Renderer for Json {
+    fn render(&self, status: &Status<Bytes>) -> Bytes {
+        if let Ok(problem) = Problem::try_from(status) {
+            return problem.to_json();
+        }
+
+        let mut problem = Problem::new(*status.code());
+        if let Some(msg) = public_message(status) {
+            problem = problem.with_detail(msg);
+        }
+        if let Some(id) = reference(status) {
+            problem = problem.with_extension("reference", id);
+        }
+        problem.to_json()
+    }
+}
+
+/// Renders the status as a minimal HTML document, with the code as its title
+/// and heading, and the message, for codes below 500, as a paragraph.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Html;
+
+impl Renderer for Html {
+    fn render(&self, status: &Status<Bytes>) -> Bytes {
+        let code = escape_html(&status.code().to_string());
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n",
+            code
+        );
+        if let Some(msg) = public_message(status) {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(msg)));
+        }
+        if let Some(id) = reference(status) {
+            html.push_str(&format!("<p>reference {}</p>\n", escape_html(id)));
+        }
+        html.push_str("</body>\n</html>\n");
+        html.into()
+    }
+}
+
+/// Returns the status's message if it may be shown to the client.
+fn public_message(status: &Status<Bytes>) -> Option<&str> {
+    if status.code().is_server_error() {
+        None
+    } else {
+        status.message()
+    }
+}
+
+/// Returns the correlation ID to show for a server error.
+fn reference(status: &Status<Bytes>) -> Option<&str> {
+    if status.code().is_server_error() {
+        status.correlation_id()
+    } else {
+        None
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Picks the body of a `Status` based on a request's `Accept` header.
+///
+/// Renderers are registered per media type, in order of preference. The
+/// renderer whose media type the client accepts with the highest quality is
+/// used, with ties going to the renderer registered first. When the client
+/// accepts none of them, or sends no `Accept` header, the first renderer is
+/// used rather than failing the request.
+///
+/// `Negotiator::default()` renders `text/plain`, `application/problem+json`,
+/// `application/json`, and `text/html`, in that order. Problem details are
+/// only labelled `application/json` for clients that do not accept
+/// `application/problem+json`.
+///
+/// Negotiation is never automatic: converting a `Status` into a warp
+/// `Response`, including in `recover`, sends its body as it is. Extract the
+/// `Accept` header with `accept()` and call `Negotiator::render` before
+/// replying.
+#[derive(Clone)]
+pub struct Negotiator {
+    renderers: Vec<(Mime, Arc<dyn Renderer>)>,
+}
+
+impl Negotiator {
+    /// Creates a negotiator without any renderers. Rendering with it leaves
+    /// statuses unchanged.
+    pub fn new() -> Self {
+        Negotiator {
+            renderers: Vec::new(),
+        }
+    }
+
+    /// Registers `renderer` for `media_type`. Parameters of `media_type`,
+    /// such as `charset`, are ignored while matching but included in the
+    /// `Content-Type` of rendered statuses.
+    pub fn renderer(mut self, media_type: Mime, renderer: impl Renderer + 'static) -> Self {
+        self.renderers.push((media_type, Arc::new(renderer)));
+        self
+    }
+
+    /// Returns the media type and renderer to use for a request with the
+    /// given `Accept` header, or `None` if there are no renderers.
+    pub fn select(&self, accept: Option<&str>) -> Option<(&Mime, &dyn Renderer)> {
+        let ranges = accept.map(parse_accept).unwrap_or_default();
+
+        let mut best: Option<(f32, usize)> = None;
+        for (idx, (media_type, _)) in self.renderers.iter().enumerate() {
+            let quality = quality(&ranges, media_type);
+            if quality > 0.0 && best.map_or(true, |(q, _)| quality > q) {
+                best = Some((quality, idx));
+            }
+        }
+
+        let idx = best.map_or(0, |(_, idx)| idx);
+        self.renderers
+            .get(idx)
+            .map(|(media_type, renderer)| (media_type, renderer.as_ref()))
+    }
+
+    /// Replaces the body of `status` with the representation that best
+    /// matches `accept`, and sets its `Content-Type` and `Vary` headers.
+    /// All other headers are kept.
+    pub fn render<T: StatusData>(&self, status: Status<T>, accept: Option<&str>) -> Status<Bytes> {
+        let mut status = status.into_bytes();
+        let (media_type, renderer) = match self.select(accept) {
+            Some(selected) => selected,
+            None => return status,
+        };
+
+        let body = renderer.render(&status);
+        let mut rendered = Status::with_data(status.c, body);
+        rendered.h = std::mem::take(&mut status.h);
+        rendered.source = status.source.take();
+        rendered.correlation_id = status.correlation_id.take();
+        rendered.h.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(media_type.as_ref()).unwrap(),
+        );
+        rendered
+            .h
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        rendered
+    }
+}
+
+impl Default for Negotiator {
+    fn default() -> Self {
+        Negotiator::new()
+            .renderer(mime::TEXT_PLAIN_UTF_8, PlainText)
+            .renderer(PROBLEM_JSON.parse().unwrap(), Json)
+            .renderer(mime::APPLICATION_JSON, Json)
+            .renderer(mime::TEXT_HTML_UTF_8, Html)
+    }
+}
+
+impl Debug for Negotiator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.renderers.iter().map(|(media_type, _)| media_type))
+            .finish()
+    }
+}
+
+/// Parses the media ranges of an `Accept` header, skipping any that are
+/// invalid, along with their quality.
+fn parse_accept(accept: &str) -> Vec<(Mime, f32)> {
+    accept
+        .split(',')
+        .filter_map(|range| range.trim().parse::<Mime>().ok())
+        .map(|range| {
+            let quality = range
+                .get_param("q")
+                .and_then(|q| q.as_str().parse::<f32>().ok())
+                .map_or(1.0, |q| q.clamp(0.0, 1.0));
+            (range, quality)
+        })
+        .collect()
+}
+
+/// Returns the quality the client gives `media_type`: that of the most
+/// specific matching range, or 0 if no range matches.
+fn quality(ranges: &[(Mime, f32)], media_type: &Mime) -> f32 {
+    ranges
+        .iter()
+        .filter_map(|(range, quality)| {
+            let specificity = if range.type_() == mime::STAR {
+                0
+            } else if range.type_() != media_type.type_() {
+                return None;
+            } else if range.subtype() == mime::STAR {
+                1
+            } else if range.subtype() != media_type.subtype() {
+                return None;
+            } else {
+                2
+            };
+            Some((specificity, *quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, quality)| quality)
+}
+
+#[cfg(feature = "server-warp")]
+/// Returns a `Filter` that extracts the request's `Accept` header, if any, for
+/// use with `Negotiator::render`.
+///
+/// Requires `features = "server-warp"`.
+pub fn accept() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Copy {
+    warp::header::optional::<String>("accept")
+}
@@ -0,0 +1,367 @@
+use crate::{Status, StatusCode, StatusData};
+use bytes::Bytes;
+use http::header::{self, HeaderValue};
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get_problem() -> Problem {
+        Problem::new(StatusCode::FORBIDDEN)
+            .with_type("https://example.com/probs/out-of-credit")
+            .with_title("You do not have enough credit.")
+            .with_detail("Your current balance is 30, but that costs 50.")
+            .with_instance("/account/12345/msgs/abc")
+            .with_extension("balance", 30)
+            .with_extension("accounts", json!(["/account/12345", "/account/67890"]))
+    }
+
+    #[test]
+    fn new_problem_uses_reason_phrase() {
+        let problem = Problem::new(StatusCode::NOT_FOUND);
+        assert_eq!(problem.type_uri(), "about:blank");
+        assert_eq!(problem.title(), Some("Not Found"));
+        assert_eq!(problem.status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem.detail(), None);
+    }
+
+    #[test]
+    fn problem_serializes_all_members() {
+        let value: Value = serde_json::from_slice(&get_problem().to_json()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": "/account/12345/msgs/abc",
+                "balance": 30,
+                "accounts": ["/account/12345", "/account/67890"],
+            })
+        );
+    }
+
+    #[test]
+    fn extensions_cannot_replace_standard_members() {
+        let problem = Problem::new(StatusCode::CONFLICT).with_extension("status", 200);
+        let value: Value = serde_json::from_slice(&problem.to_json()).unwrap();
+        assert_eq!(value["status"], json!(409));
+    }
+
+    #[test]
+    fn status_with_problem_is_problem_json() {
+        let status = Status::with_problem(get_problem());
+        assert_eq!(status.code(), &StatusCode::FORBIDDEN);
+        assert_eq!(
+            status.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        assert_eq!(status.bytes(), &get_problem().to_json()[..]);
+    }
+
+    #[test]
+    fn server_error_problems_name_the_reference() {
+        let status = Status::with_problem(Problem::new(StatusCode::BAD_GATEWAY));
+        let id = status.correlation_id().unwrap();
+        assert_eq!(status.data().extension("reference"), Some(&json!(id)));
+
+        let problem = Problem::new(StatusCode::BAD_GATEWAY).with_extension("reference", "mine");
+        let status = Status::with_problem(problem);
+        assert_eq!(status.data().extension("reference"), Some(&json!("mine")));
+    }
+
+    #[test]
+    fn problem_round_trips_through_status_bytes() {
+        let status = Status::with_problem(get_problem());
+        let mut received = Status::with_data(*status.code(), Bytes::from(status.bytes().to_vec()));
+        *received.headers_mut() = status.headers().clone();
+
+        assert_eq!(Problem::try_from(&received).unwrap(), get_problem());
+    }
+
+    #[test]
+    fn parsing_ignores_members_of_the_wrong_type() {
+        let mut status = Status::with_data(
+            StatusCode::BAD_REQUEST,
+            Bytes::from_static(br#"{"type": 5, "title": "Bad", "status": "400", "detail": null}"#),
+        );
+        status.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json; charset=utf-8"),
+        );
+
+        let problem = Problem::try_from(&status).unwrap();
+        assert_eq!(problem.type_uri(), "about:blank");
+        assert_eq!(problem.title(), Some("Bad"));
+        assert_eq!(problem.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem.detail(), None);
+        assert!(problem.extensions().is_empty());
+    }
+
+    #[test]
+    fn parsing_requires_problem_json() {
+        let status = Status::with_message(StatusCode::BAD_REQUEST, String::from("{}"));
+        assert!(matches!(
+            Problem::try_from(&status),
+            Err(Error::NotProblem(_))
+        ));
+
+        let mut status = Status::with_data(StatusCode::BAD_REQUEST, Bytes::from_static(b"[]"));
+        status
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        assert!(matches!(Problem::try_from(&status), Err(Error::NotObject)));
+    }
+}
+
+/// The media type of a serialized `Problem`.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// The `type` of a problem that has no further semantics than its status
+/// code.
+const ABOUT_BLANK: &str = "about:blank";
+
+/// Names of the members defined by RFC 9457, which extension members cannot
+/// use.
+const STANDARD_MEMBERS: [&str; 5] = ["type", "title", "status", "detail", "instance"];
+
+/// An error encountered while parsing a `Problem` from a response.
+#[derive(Debug)]
+pub enum Error {
+    /// The response's `Content-Type` is not `application/problem+json`. The
+    /// actual content type, if any, is included.
+    NotProblem(Option<String>),
+    /// The response body is not valid JSON.
+    Json(serde_json::Error),
+    /// The response body is JSON, but not an object.
+    NotObject,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::NotProblem(None) => write!(f, "response has no content type"),
+            Error::NotProblem(Some(content_type)) => {
+                write!(f, "expected {}, got {}", PROBLEM_JSON, content_type)
+            }
+            Error::Json(err) => write!(f, "invalid problem details: {}", err),
+            Error::NotObject => write!(f, "problem details are not a JSON object"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Machine-readable details of an error, as described by RFC 9457 (which
+/// obsoletes RFC 7807), serialized as `application/problem+json`.
+///
+/// Attach a `Problem` to a response with `Status::with_problem`, and parse one
+/// back out of a received `Status` with `Problem::try_from`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    type_uri: String,
+    title: Option<String>,
+    status: StatusCode,
+    detail: Option<String>,
+    instance: Option<String>,
+    extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Creates a problem of type `about:blank` for the given status code,
+    /// titled with the code's reason phrase.
+    pub fn new(code: StatusCode) -> Self {
+        Problem {
+            type_uri: String::from(ABOUT_BLANK),
+            title: code.canonical_reason().map(String::from),
+            status: code,
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Sets the URI reference that identifies the problem type.
+    pub fn with_type(mut self, type_uri: impl Into<String>) -> Self {
+        self.type_uri = type_uri.into();
+        self
+    }
+
+    /// Sets a short, human-readable summary of the problem type.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets a human-readable explanation specific to this occurrence of the
+    /// problem.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Sets a URI reference that identifies this occurrence of the problem.
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Adds an extension member. Extensions named like one of the standard
+    /// members are ignored when the problem is serialized.
+    pub fn with_extension(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extensions.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the URI reference that identifies the problem type.
+    pub fn type_uri(&self) -> &str {
+        &self.type_uri
+    }
+
+    /// Returns the short summary of the problem type, if any.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Returns the status code of the response the problem belongs to.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the explanation of this occurrence of the problem, if any.
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// Returns the URI reference of this occurrence of the problem, if any.
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// Returns the value of the extension member `name`, if any.
+    pub fn extension(&self, name: &str) -> Option<&Value> {
+        self.extensions.get(name)
+    }
+
+    /// Returns all extension members.
+    pub fn extensions(&self) -> &Map<String, Value> {
+        &self.extensions
+    }
+
+    /// Serializes the problem as an `application/problem+json` body.
+    pub fn to_json(&self) -> Bytes {
+        let mut object: Map<String, Value> = self
+            .extensions
+            .iter()
+            .filter(|(name, _)| !STANDARD_MEMBERS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        object.insert(String::from("type"), Value::from(self.type_uri.as_str()));
+        if let Some(title) = &self.title {
+            object.insert(String::from("title"), Value::from(title.as_str()));
+        }
+        object.insert(String::from("status"), Value::from(self.status.as_u16()));
+        if let Some(detail) = &self.detail {
+            object.insert(String::from("detail"), Value::from(detail.as_str()));
+        }
+        if let Some(instance) = &self.instance {
+            object.insert(String::from("instance"), Value::from(instance.as_str()));
+        }
+
+        Value::Object(object).to_string().into()
+    }
+
+    /// Parses an `application/problem+json` body sent with a response with
+    /// the given status code.
+    ///
+    /// As RFC 9457 requires, standard members whose value has the wrong type
+    /// are ignored. The response's status code is used even if the body's
+    /// `status` member differs, as the member is only advisory.
+    pub fn from_json(code: StatusCode, body: &[u8]) -> Result<Self, Error> {
+        let mut object = match serde_json::from_slice(body).map_err(Error::Json)? {
+            Value::Object(object) => object,
+            _ => return Err(Error::NotObject),
+        };
+
+        let mut take_str = |name: &str| match object.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        };
+
+        let type_uri = take_str("type").unwrap_or_else(|| String::from(ABOUT_BLANK));
+        let title = take_str("title");
+        let detail = take_str("detail");
+        let instance = take_str("instance");
+        object.remove("status");
+
+        Ok(Problem {
+            type_uri,
+            title,
+            status: code,
+            detail,
+            instance,
+            extensions: object,
+        })
+    }
+}
+
+impl From<Problem> for Bytes {
+    fn from(problem: Problem) -> Bytes {
+        problem.to_json()
+    }
+}
+
+impl<T: StatusData> TryFrom<&Status<T>> for Problem {
+    type Error = Error;
+
+    /// Parses the body of a received `Status` whose `Content-Type` is
+    /// `application/problem+json`.
+    fn try_from(status: &Status<T>) -> Result<Self, Self::Error> {
+        let content_type = status
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let is_problem = content_type
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .is_some_and(|mime_type| mime_type.essence_str() == PROBLEM_JSON);
+        if !is_problem {
+            return Err(Error::NotProblem(content_type.map(String::from)));
+        }
+
+        Problem::from_json(*status.code(), status.bytes())
+    }
+}
+
+impl Status {
+    /// Create a new Status carrying problem details, with the problem's status
+    /// code and a `Content-Type` of `application/problem+json`.
+    ///
+    /// Problems for server errors get a `reference` extension member holding
+    /// the Status's correlation ID, unless they already have one.
+    pub fn with_problem(problem: Problem) -> Status<Problem> {
+        let code = problem.status();
+        let mut status = Status::with_data(code, problem);
+        if let Some(id) = status.correlation_id.clone() {
+            if status.data.extension("reference").is_none() {
+                status.data = status.data.clone().with_extension("reference", id);
+            }
+        }
+        status
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        status
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{Rejected, Status, StatusCode};
+use bytes::Bytes;
+use std::convert::Infallible;
+use std::fmt::Display;
+use warp::filters::{body::BodyDeserializeError, cors::CorsForbidden};
+use warp::reject::{
+    InvalidHeader, InvalidQuery, LengthRequired, MethodNotAllowed, MissingCookie, MissingHeader,
+    PayloadTooLarge, Rejection, UnsupportedMediaType,
+};
+use warp::reply::Response;
+
+/// Recovers any warp `Rejection` as a response, for use with
+/// `warp::Filter::recover`.
+///
+/// The response is built by `Status::from_rejection` and sent like any other
+/// `Status`, so server errors hide their message and carry a correlation ID.
+/// Requires `features = "server-warp"`.
+pub async fn recover(err: Rejection) -> Result<Response, Infallible> {
+    Ok(Status::from_rejection(&err).into())
+}
+
+/// Finds a warp rejection of type `C` in `err` and returns a Status with
+/// `code` and the rejection's description as its message.
+fn translate<C: Display + 'static>(err: &Rejection, code: StatusCode) -> Option<Status<Bytes>> {
+    err.find::<C>()
+        .map(|cause| Status::with_message(code, cause.to_string()).into_bytes())
+}
+
+impl Status {
+    /// Turns any warp `Rejection` into a `Status<Bytes>`.
+    ///
+    /// A `Status` converted into the rejection is returned as it was
+    /// rejected, whatever its data type.
+    ///
+    /// Warp's own rejections become the matching client error, with warp's
+    /// description as the message. Anything else becomes a
+    /// `500 Internal Server Error` whose hidden message describes the
+    /// rejection. Requires `features = "server-warp"`.
+    pub fn from_rejection(err: &Rejection) -> Status<Bytes> {
+        if let Some(rejected) = err.find::<Rejected>() {
+            return rejected.status.clone();
+        }
+
+        if err.is_not_found() {
+            return Status::new(StatusCode::NOT_FOUND).into_bytes();
+        }
+
+        translate::<MethodNotAllowed>(err, StatusCode::METHOD_NOT_ALLOWED)
+            .or_else(|| translate::<LengthRequired>(err, StatusCode::LENGTH_REQUIRED))
+            .or_else(|| translate::<PayloadTooLarge>(err, StatusCode::PAYLOAD_TOO_LARGE))
+            .or_else(|| translate::<UnsupportedMediaType>(err, StatusCode::UNSUPPORTED_MEDIA_TYPE))
+            .or_else(|| translate::<CorsForbidden>(err, StatusCode::FORBIDDEN))
+            .or_else(|| translate::<MissingHeader>(err, StatusCode::BAD_REQUEST))
+            .or_else(|| translate::<InvalidHeader>(err, StatusCode::BAD_REQUEST))
+            .or_else(|| translate::<MissingCookie>(err, StatusCode::BAD_REQUEST))
+            .or_else(|| translate::<InvalidQuery>(err, StatusCode::BAD_REQUEST))
+            .or_else(|| translate::<BodyDeserializeError>(err, StatusCode::BAD_REQUEST))
+            .unwrap_or_else(|| {
+                let msg = format!("unhandled rejection: {:?}", err);
+                Status::with_message(StatusCode::INTERNAL_SERVER_ERROR, msg).into_bytes()
+            })
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Status, StatusData};
+
+/// Reports a Status that is being sent as a response.
+///
+/// With the `tracing` feature, every Status is recorded as an event whose
+/// level follows the status class: `ERROR` for server errors, `WARN` for
+/// client errors, `DEBUG` for redirects and successes, and `TRACE` for
+/// informational responses. Client errors include their message; server
+/// errors include the message and source hidden from the client, and the
+/// correlation ID.
+///
+/// Server errors are also logged through the `log` crate, with or without
+/// `tracing`, so applications that only collect `log` output keep them.
+pub(crate) fn report<T: StatusData>(status: &Status<T>) {
+    let code = status.code();
+    let source = status.source.as_ref().map(|err| err.to_string());
+
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::{event, Level};
+
+        // `message` is the name of an event's own message
+        let status_message = status.message().unwrap_or_default();
+        let correlation_id = status.correlation_id().unwrap_or_default();
+        let source = source.as_deref().unwrap_or_default();
+        let code = code.as_u16();
+        if status.code().is_server_error() {
+            event!(
+                Level::ERROR,
+                code,
+                correlation_id,
+                status_message,
+                source,
+                "server error response"
+            );
+        } else if status.code().is_client_error() {
+            event!(Level::WARN, code, status_message, "client error response");
+        } else if status.code().is_informational() {
+            event!(Level::TRACE, code, "informational response");
+        } else {
+            event!(Level::DEBUG, code, "response");
+        }
+    }
+
+    if code.is_server_error() {
+        let source = source
+            .map(|err| format!(", caused by: {}", err))
+            .unwrap_or_default();
+        log::error!(
+            "{} (reference {}): {}{}",
+            code,
+            status.correlation_id().unwrap_or("none"),
+            status.message().unwrap_or("no message"),
+            source
+        );
+    }
+}
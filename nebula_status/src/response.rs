@@ -0,0 +1,90 @@
+use crate::{correlation, Status};
+use bytes::Bytes;
+#[cfg(feature = "hyper")]
+use bytes::BytesMut;
+#[cfg(feature = "hyper")]
+use hyper::body::{Body, HttpBody};
+#[cfg(feature = "hyper")]
+use std::fmt::{self, Display, Formatter};
+
+/// An error encountered while reading a hyper response into a `Status`.
+/// Requires `features = "hyper"`.
+#[cfg(feature = "hyper")]
+#[derive(Debug)]
+pub enum Error {
+    /// The body is longer than the limit, in bytes, that is included.
+    TooLarge(usize),
+    /// The body could not be read.
+    Body(hyper::Error),
+}
+
+#[cfg(feature = "hyper")]
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::TooLarge(limit) => write!(f, "response body is over {} bytes", limit),
+            Error::Body(err) => write!(f, "failed to read response body: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Body(err) => Some(err),
+            Error::TooLarge(_) => None,
+        }
+    }
+}
+
+impl From<http::Response<Bytes>> for Status<Bytes> {
+    /// Keeps the response's status code, headers, and body as they are. The
+    /// correlation ID is read from the `X-Correlation-ID` header, if any.
+    fn from(response: http::Response<Bytes>) -> Self {
+        let (parts, body) = response.into_parts();
+        let mut status = Status::with_data(parts.status, body);
+        status.correlation_id = parts
+            .headers
+            .get(correlation::HEADER)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| correlation::is_valid(id))
+            .map(String::from);
+        status.h = parts.headers;
+        status
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl Status {
+    /// Reads a hyper response into a `Status<Bytes>`, like the conversion from
+    /// an `http::Response<Bytes>`.
+    ///
+    /// Returns `Error::TooLarge` without reading further once the body, or its
+    /// `Content-Length`, is over `limit` bytes. Requires
+    /// `features = "hyper"`.
+    pub async fn from_hyper_response(
+        response: hyper::Response<Body>,
+        limit: usize,
+    ) -> Result<Status<Bytes>, Error> {
+        let (parts, mut body) = response.into_parts();
+        let too_large = body
+            .size_hint()
+            .exact()
+            .is_some_and(|len| len > limit as u64);
+        if too_large {
+            return Err(Error::TooLarge(limit));
+        }
+
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(Error::Body)?;
+            if bytes.len() + chunk.len() > limit {
+                return Err(Error::TooLarge(limit));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(http::Response::from_parts(parts, bytes.freeze()).into())
+    }
+}